@@ -34,6 +34,8 @@ last-update = Zeitpunkt der letzten Aktualisierung:
 up-since = Uptime: { $duration }
 scan-duration = { $valid }/{ $total } Pakete wurden in { $duration } Sekunden durchsucht.
 generated-in = Die XML-Ausgabe wurde in { $duration } Millisekunden generiert.
+feed-title = { product-name }: Neue Versionen
+feed-title-package = { product-name }: Neue Versionen von { $package }
 
 package-list-unavailable = Die Paketliste ist momentan nicht verfügbar. Bitte probieren Sie es später erneut.
 package-unknown = "Unbekanntes Paket: { $package_id }"
@@ -42,3 +44,9 @@ package-read-failed = Das Paket { $file } konnte nicht heruntergeladen werden. W
 file-not-found = Datei nicht gefunden
 unacceptable-accept-type = Unbekannter "Accept"-Header-Wert: { $value }
 package-payment-required = Sie sind nicht dazu authorisiert, das Paket { $package_id } in Version { $version } abzurufen.
+purchase-hint = Sie können nicht auf alle Versionen dieses Pakets zugreifen, eine Lizenz kann erworben werden.
+purchase-store = Shop
+purchase-price = Preis
+purchase-contact = Kontakt
+too-many-login-failures = Zu viele fehlgeschlagene Anmeldeversuche, bitte versuchen Sie es in { $seconds } Sekunden erneut.
+download-quota-exceeded = Sie haben Ihr Download-Kontingent überschritten, bitte versuchen Sie es in { $seconds } Sekunden erneut.
@@ -34,6 +34,8 @@ last-update = Last update:
 up-since = Uptime: { $duration }
 scan-duration = Scanned { $valid }/{ $total } packages in { $duration } seconds.
 generated-in = XML generated in { $duration } milliseconds.
+feed-title = { product-name }: New releases
+feed-title-package = { product-name }: New releases of { $package }
 
 package-list-unavailable = The package list is unavailable right now, please try again later.
 package-unknown = Unknown package: { $package_id }
@@ -42,3 +44,9 @@ package-read-failed = The package { $file } could not be downloaded. If this err
 file-not-found = File Not Found
 unacceptable-accept-type = Unacceptable "Accept" header value: { $value }
 package-payment-required = You are not authorized to access the package { $package_id } in version { $version }.
+purchase-hint = You may not access all versions of this package, a licence can be purchased.
+purchase-store = Store
+purchase-price = Price
+purchase-contact = Contact
+too-many-login-failures = Too many failed login attempts, please try again in { $seconds } seconds.
+download-quota-exceeded = You have exceeded your download quota, please try again in { $seconds } seconds.
@@ -0,0 +1,248 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! An append-only log of downloads, denied downloads and logins,
+//! written as one JSON object per line.
+
+use crate::{date::Date, SETTINGS};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    /// A version was downloaded with the permissions of the user.
+    Downloaded,
+    /// A version was downloaded using a signed download link.
+    DownloadedSigned,
+    /// A download was denied and credentials were requested (401).
+    Unauthorized,
+    /// A download was denied and the user was told to purchase the package (402).
+    PaymentRequired,
+    /// A download was denied because the user’s download quota was exhausted (429).
+    QuotaExceeded,
+    /// A user signed in using the login page.
+    LoginSucceeded,
+    /// The credentials of a request were invalid.
+    LoginFailed,
+    /// A login was rejected without verifying the credentials after too many failures.
+    LoginLockedOut,
+}
+
+/// A single entry of the audit log.
+#[derive(Debug, Serialize)]
+pub struct Record<'a> {
+    #[serde(serialize_with = "serialize_timestamp")]
+    pub timestamp: SystemTime,
+    pub outcome: Outcome,
+    pub user: Option<&'a str>,
+    pub token: Option<&'a str>,
+    pub licence: Option<&'a str>,
+    pub ip: Option<IpAddr>,
+    pub package: Option<&'a str>,
+    pub version: Option<String>,
+    pub api_version: Option<&'a str>,
+}
+
+impl<'a> Record<'a> {
+    pub fn new(outcome: Outcome) -> Self {
+        Self {
+            timestamp: SystemTime::now(),
+            outcome,
+            user: None,
+            token: None,
+            licence: None,
+            ip: None,
+            package: None,
+            version: None,
+            api_version: None,
+        }
+    }
+}
+
+fn serialize_timestamp<S: serde::Serializer>(
+    timestamp: &SystemTime,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&humantime::format_rfc3339_seconds(*timestamp))
+}
+
+#[derive(Debug)]
+struct OpenFile {
+    file: File,
+    size: u64,
+    date: Date,
+}
+
+/// Appends records to a file, rotating it once it would exceed `max_size`
+/// bytes or, if `daily` is set, once the date changes.
+///
+/// Rotated files are renamed to `<path>.<date>.<n>`, with `<date>` being the
+/// date of their last record.
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    max_size: u64,
+    daily: bool,
+    file: Mutex<Option<OpenFile>>,
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf, max_size: u64, daily: bool) -> Self {
+        Self {
+            path,
+            max_size,
+            daily,
+            file: Mutex::new(None),
+        }
+    }
+
+    fn open(path: &Path) -> std::io::Result<OpenFile> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let metadata = file.metadata()?;
+
+        Ok(OpenFile {
+            size: metadata.len(),
+            date: metadata.modified().map(Date::from)?,
+            file,
+        })
+    }
+
+    fn rotate(&self, date: Date) -> std::io::Result<()> {
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+
+        let rotated = (1..)
+            .map(|n| self.path.with_file_name(format!("{name}.{date}.{n}")))
+            .find(|rotated| !rotated.exists())
+            .unwrap();
+
+        std::fs::rename(&self.path, rotated)
+    }
+
+    pub fn write(&self, record: &Record) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        let today = Date::from(record.timestamp);
+        let mut file = self.file.lock().unwrap();
+
+        if file.is_none() {
+            *file = Some(Self::open(&self.path)?);
+        }
+
+        if let Some(open) = file.as_ref() {
+            let is_full =
+                self.max_size > 0 && open.size > 0 && open.size + line.len() as u64 > self.max_size;
+            let is_outdated = self.daily && open.date != today;
+
+            if is_full || is_outdated {
+                let date = open.date;
+
+                *file = None;
+                self.rotate(date)?;
+                *file = Some(Self::open(&self.path)?);
+            }
+        }
+
+        let open = file.as_mut().unwrap();
+        open.file.write_all(&line)?;
+        open.size += line.len() as u64;
+        open.date = today;
+
+        Ok(())
+    }
+}
+
+static AUDIT_LOG: Lazy<Option<AuditLog>> = Lazy::new(|| {
+    SETTINGS.audit_log.as_ref().map(|path| {
+        AuditLog::new(
+            path.clone(),
+            SETTINGS.audit_log_max_size,
+            SETTINGS.audit_log_daily,
+        )
+    })
+});
+
+/// Appends the record to the configured audit log, if any.
+pub fn record(record: Record) {
+    if let Some(audit_log) = AUDIT_LOG.as_ref() {
+        if let Err(err) = audit_log.write(&record) {
+            log::error!(
+                "Failed to write to audit log \"{}\": {}",
+                audit_log.path.display(),
+                err
+            );
+        }
+    }
+}
+
+#[test]
+fn test_write() {
+    use std::time::Duration;
+
+    let dir = std::env::temp_dir().join(format!("audit-log-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("audit.log");
+
+    let day = |day: u64| SystemTime::UNIX_EPOCH + Duration::from_secs(day * 86_400);
+    let record = |timestamp| Record {
+        timestamp,
+        user: Some("Customer"),
+        ip: Some("192.0.2.1".parse().unwrap()),
+        package: Some("be.bastelstu.example"),
+        version: Some("1.0.0".to_owned()),
+        api_version: Some("2.1"),
+        ..Record::new(Outcome::Downloaded)
+    };
+
+    let audit_log = AuditLog::new(path.clone(), 400, true);
+    audit_log.write(&record(day(1))).unwrap();
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        contents,
+        "{\"timestamp\":\"1970-01-02T00:00:00Z\",\"outcome\":\"downloaded\",\"user\":\"Customer\",\"token\":null,\"licence\":null,\"ip\":\"192.0.2.1\",\"package\":\"be.bastelstu.example\",\"version\":\"1.0.0\",\"api_version\":\"2.1\"}\n"
+    );
+
+    // Rotated once the size is exceeded
+    audit_log.write(&record(day(1))).unwrap();
+    audit_log.write(&record(day(1))).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(dir.join("audit.log.1970-01-02.1"))
+            .unwrap()
+            .lines()
+            .count(),
+        2
+    );
+    assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+
+    // Rotated once the date changes
+    audit_log.write(&record(day(2))).unwrap();
+    assert!(dir.join("audit.log.1970-01-02.2").exists());
+    assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
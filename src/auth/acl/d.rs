@@ -0,0 +1,63 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::date::Date;
+
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    combinator::{map, value},
+    IResult, Parser,
+};
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum D {
+    ReleaseDateInput,
+    Date(Date),
+}
+
+impl D {
+    pub fn parser(input: &str) -> IResult<&str, D> {
+        parser(input)
+    }
+}
+
+/// Parses either `$released` or a valid date.
+fn parser(input: &str) -> IResult<&str, D> {
+    alt((
+        value(D::ReleaseDateInput, tag("$released")),
+        map(Date::parser, D::Date),
+    ))
+    .parse(input)
+}
+
+#[test]
+fn test_parser() {
+    assert_eq!(parser("$released"), Ok(("", D::ReleaseDateInput)));
+    assert_eq!(
+        parser("$released < 2025-06-01"),
+        Ok((" < 2025-06-01", D::ReleaseDateInput))
+    );
+    assert_eq!(
+        parser("2025-06-01"),
+        Ok(("", D::Date(Date::new(2025, 6, 1))))
+    );
+
+    assert!(parser("$v").is_err());
+    assert!(parser("1.0.0").is_err());
+}
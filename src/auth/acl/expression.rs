@@ -16,17 +16,14 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use super::{relation::Relation, v::V};
-use crate::{
-    nom::ws,
-    version::{SuffixType, Version},
-};
+use super::{d::D, relation::Relation, v::V, Subject};
+use crate::{nom::ws, version::SuffixType};
 use nom::{
     branch::alt,
     bytes::complete::tag,
     character::complete::char,
     combinator::map,
-    multi::many1,
+    multi::many0,
     sequence::{delimited, preceded, separated_pair},
     IResult, Parser,
 };
@@ -39,14 +36,18 @@ pub enum Expression {
     Equals(V, V),
     Less(V, V),
     Like(SuffixType),
+    DateEquals(D, D),
+    DateLess(D, D),
 }
 
 impl Expression {
-    pub fn evaluate(&self, v: &Version) -> bool {
+    pub fn evaluate(&self, subject: &Subject) -> bool {
+        let v = subject.version;
+
         match self {
-            Expression::Not(e) => !e.evaluate(v),
-            Expression::And(e1, e2) => e1.evaluate(v) && e2.evaluate(v),
-            Expression::Or(e1, e2) => e1.evaluate(v) || e2.evaluate(v),
+            Expression::Not(e) => !e.evaluate(subject),
+            Expression::And(e1, e2) => e1.evaluate(subject) && e2.evaluate(subject),
+            Expression::Or(e1, e2) => e1.evaluate(subject) || e2.evaluate(subject),
 
             Expression::Equals(V::VersionInput, V::Version(v2)) => *v == *v2,
             Expression::Equals(V::Version(v1), V::VersionInput) => *v1 == *v,
@@ -65,6 +66,16 @@ impl Expression {
 
                 false
             }
+
+            // Comparisons against an unknown release date never match
+            Expression::DateEquals(d1, d2) => match (subject.date(d1), subject.date(d2)) {
+                (Some(d1), Some(d2)) => d1 == d2,
+                _ => false,
+            },
+            Expression::DateLess(d1, d2) => match (subject.date(d1), subject.date(d2)) {
+                (Some(d1), Some(d2)) => d1 < d2,
+                _ => false,
+            },
         }
     }
 
@@ -84,46 +95,68 @@ impl From<(V, Relation, V)> for Expression {
     }
 }
 
-/// Parses a logical conjunction: Several sub expressions combined using `&&`.
-fn and(input: &str) -> IResult<&str, Expression> {
+impl From<(D, Relation, D)> for Expression {
+    fn from((d1, r, d2): (D, Relation, D)) -> Self {
+        match r {
+            Relation::Greater => Expression::DateLess(d2, d1),
+            Relation::GreaterOrEquals => Expression::Or(
+                Box::new(Expression::DateLess(d2, d1)),
+                Box::new(Expression::DateEquals(d1, d2)),
+            ),
+            Relation::Less => Expression::DateLess(d1, d2),
+            Relation::LessOrEquals => Expression::Or(
+                Box::new(Expression::DateLess(d1, d2)),
+                Box::new(Expression::DateEquals(d1, d2)),
+            ),
+        }
+    }
+}
+
+/// Parses a logical disjunction: One or more conjunctions combined using `||`.
+///
+/// `&&` binds tighter than `||`, thus `a && b || c` is parsed as `(a && b) || c`.
+fn or(input: &str) -> IResult<&str, Expression> {
     map(
-        (
-            ws(sub_expression),
-            many1(preceded(ws(tag("&&")), sub_expression)),
-        ),
+        (ws(and), many0(preceded(ws(tag("||")), and))),
         |(e1, e_list)| {
-            assert!(!e_list.is_empty());
-
-            let e2 = e_list
+            e_list
                 .into_iter()
-                .reduce(|a, b| Expression::And(Box::new(a), Box::new(b)))
-                .unwrap();
-
-            Expression::And(Box::new(e1), Box::new(e2))
+                .fold(e1, |a, b| Expression::Or(Box::new(a), Box::new(b)))
         },
     )
     .parse(input)
 }
 
-/// Parses a logical disjunction: Several sub expressions combined using `||`.
-fn or(input: &str) -> IResult<&str, Expression> {
+/// Parses a logical conjunction: One or more negations or sub expressions combined using `&&`.
+fn and(input: &str) -> IResult<&str, Expression> {
     map(
-        (
-            ws(sub_expression),
-            many1(preceded(ws(tag("||")), sub_expression)),
-        ),
+        (ws(not), many0(preceded(ws(tag("&&")), ws(not)))),
         |(e1, e_list)| {
-            let e2 = e_list
+            e_list
                 .into_iter()
-                .reduce(|a, b| Expression::Or(Box::new(a), Box::new(b)))
-                .unwrap();
-
-            Expression::Or(Box::new(e1), Box::new(e2))
+                .fold(e1, |a, b| Expression::And(Box::new(a), Box::new(b)))
         },
     )
     .parse(input)
 }
 
+/// Parses either a logical negation or a sub expression.
+fn not(input: &str) -> IResult<&str, Expression> {
+    alt((negation, sub_expression)).parse(input)
+}
+
+/// Parses a logical negation of an expression within parentheses: `!(<expression>)`.
+fn negation(input: &str) -> IResult<&str, Expression> {
+    map(
+        preceded(
+            char('!'),
+            ws(alt((negation, delimited(char('('), parser, char(')'))))),
+        ),
+        |e| Expression::Not(Box::new(e)),
+    )
+    .parse(input)
+}
+
 /// Parses valid sub expressions:
 /// - An expression within parentheses.
 /// - `$v ~ <suffix_type>`
@@ -132,6 +165,10 @@ fn or(input: &str) -> IResult<&str, Expression> {
 /// - `<v> <relation> <v> <relation> <v>`
 /// - `<v> <relation> <v>`
 /// - `<v> = <v>`
+/// - `<d> != <d>`
+/// - `<d> <relation> <d> <relation> <d>`
+/// - `<d> <relation> <d>`
+/// - `<d> = <d>`
 fn sub_expression(input: &str) -> IResult<&str, Expression> {
     alt((
         delimited(char('('), parser, char(')')),
@@ -167,14 +204,54 @@ fn sub_expression(input: &str) -> IResult<&str, Expression> {
             separated_pair(V::parser, ws(char('=')), V::parser),
             |(v1, v2)| Expression::Equals(v1, v2),
         ),
+        date_sub_expression,
+    ))
+    .parse(input)
+}
+
+/// Parses the sub expressions comparing dates, see [`sub_expression`].
+fn date_sub_expression(input: &str) -> IResult<&str, Expression> {
+    alt((
+        map(
+            separated_pair(D::parser, ws(tag("!=")), D::parser),
+            |(d1, d2)| {
+                Expression::Or(
+                    Box::new(Expression::DateLess(d1, d2)),
+                    Box::new(Expression::DateLess(d2, d1)),
+                )
+            },
+        ),
+        map(
+            (
+                D::parser,
+                ws(Relation::parser),
+                D::parser,
+                ws(Relation::parser),
+                D::parser,
+            ),
+            |(d1, r1, d2, r2, d3)| {
+                let left = (d1, r1, d2).into();
+                let right = (d2, r2, d3).into();
+
+                Expression::And(Box::new(left), Box::new(right))
+            },
+        ),
+        map((D::parser, ws(Relation::parser), D::parser), Into::into),
+        map(
+            separated_pair(D::parser, ws(char('=')), D::parser),
+            |(d1, d2)| Expression::DateEquals(d1, d2),
+        ),
     ))
     .parse(input)
 }
 
 /// Parses a valid expression:
 /// - A subexpression.
+/// - A negated expression within parentheses (not).
 /// - A logical conjunction (and).
-/// - A logical disjunection (or).
+/// - A logical disjunction (or).
+///
+/// The operators are listed in the order of their precedence, from highest to lowest.
 fn parser(input: &str) -> IResult<&str, Expression> {
-    alt((and, or, sub_expression)).parse(input)
+    or(input)
 }
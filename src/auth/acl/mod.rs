@@ -16,9 +16,41 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+mod d;
 mod expression;
 mod relation;
 mod ruleset;
 mod v;
 
 pub use self::ruleset::Ruleset;
+
+use self::d::D;
+use crate::{date::Date, version::Version};
+
+/// The package version a ruleset is evaluated against.
+#[derive(Debug, Clone, Copy)]
+pub struct Subject<'a> {
+    /// The value of `$v`.
+    pub version: &'a Version,
+
+    /// The value of `$released`, if the release date is known.
+    pub released: Option<Date>,
+}
+
+impl Subject<'_> {
+    fn date(&self, d: &D) -> Option<Date> {
+        match d {
+            D::ReleaseDateInput => self.released,
+            D::Date(date) => Some(*date),
+        }
+    }
+}
+
+impl<'a> From<&'a Version> for Subject<'a> {
+    fn from(version: &'a Version) -> Self {
+        Self {
+            version,
+            released: None,
+        }
+    }
+}
@@ -18,8 +18,8 @@
 
 use serde::{de::Visitor, Deserialize, Deserializer};
 
-use super::expression::Expression;
-use crate::{nom::ws, version::Version};
+use super::{expression::Expression, Subject};
+use crate::nom::ws;
 use nom::{
     branch::alt,
     character::complete::char,
@@ -35,10 +35,10 @@ pub enum Ruleset {
 }
 
 impl Ruleset {
-    pub fn evaluate(&self, v: &Version) -> bool {
+    pub fn evaluate<'a>(&self, subject: impl Into<Subject<'a>>) -> bool {
         match self {
             Ruleset::Star => true,
-            Ruleset::Expression(e) => e.evaluate(v),
+            Ruleset::Expression(e) => e.evaluate(&subject.into()),
         }
     }
 
@@ -99,8 +99,8 @@ fn test_parser() {
     assert!(parser("$v !~ beta || $v !~ alpha || $v !~ dev").is_ok());
     assert!(parser("$v !~ beta && $v !~ alpha").is_ok());
     assert!(parser("$v !~ beta && $v !~ alpha && $v !~ dev").is_ok());
-    assert!(parser("$v !~ beta && $v !~ alpha || $v !~ dev").is_err());
-    assert!(parser("$v !~ beta || $v !~ alpha && $v !~ dev").is_err());
+    assert!(parser("$v !~ beta && $v !~ alpha || $v !~ dev").is_ok());
+    assert!(parser("$v !~ beta || $v !~ alpha && $v !~ dev").is_ok());
     assert!(parser("($v ~ beta)").is_ok());
     assert!(parser("($v ~ beta || $v ~ alpha)").is_ok());
     assert!(parser("($v~beta||$v~alpha)").is_ok());
@@ -112,10 +112,26 @@ fn test_parser() {
     assert!(parser("($v !~ beta && $v !~ alpha && 1.0.0 = 2.0.0)").is_ok());
     assert!(parser("$v!~beta&&$v!~alpha&&1.0.0=2.0.0").is_ok());
     assert!(parser("($v!~beta&&$v!~alpha&&1.0.0=2.0.0)").is_ok());
+    assert!(parser("!($v ~ beta)").is_ok());
+    assert!(parser("! ($v ~ beta || $v ~ alpha)").is_ok());
+    assert!(parser("!!($v ~ beta)").is_ok());
+    assert!(parser("$v = 1.0.0 && !($v ~ beta || $v ~ alpha)").is_ok());
+    assert!(parser("!$v ~ beta").is_err());
+    assert!(parser("!1.0.0 = 2.0.0").is_err());
+    assert!(parser("$v ~ beta &&").is_err());
+    assert!(parser("|| $v ~ beta").is_err());
+    assert!(parser("$released < 2025-06-01").is_ok());
+    assert!(parser("2025-01-01 <= $released < 2025-06-01").is_ok());
+    assert!(parser("$released = 2025-06-01 || $released != 2025-06-02").is_ok());
+    assert!(parser("$v >= 2.0.0 && $released <= 2025-06-01").is_ok());
+    assert!(parser("$released < 1.0.0").is_err());
+    assert!(parser("$v < 2025-06-01").is_err());
 }
 
 #[test]
 fn test_evaluate() {
+    use crate::version::Version;
+
     assert!(parser("$v ~ beta")
         .unwrap()
         .evaluate(&Version::parser("1.0.0 Beta 1").unwrap().1));
@@ -162,4 +178,63 @@ fn test_evaluate() {
         assert!(rs.evaluate(&Version::parser("4.0.0").unwrap().1));
         assert!(!rs.evaluate(&Version::parser("5.0.0").unwrap().1));
     }
+
+    {
+        // `&&` binds tighter than `||`
+        let rs = parser("$v ~ beta && $v < 2.0.0 Alpha 1 || $v ~ rc").unwrap();
+        assert!(rs.evaluate(&Version::parser("1.0.0 Beta 1").unwrap().1));
+        assert!(!rs.evaluate(&Version::parser("2.0.0 Beta 1").unwrap().1));
+        assert!(rs.evaluate(&Version::parser("2.0.0 RC 1").unwrap().1));
+        assert!(!rs.evaluate(&Version::parser("1.0.0").unwrap().1));
+
+        let rs = parser("$v ~ rc || $v ~ beta && $v < 2.0.0 Alpha 1").unwrap();
+        assert!(rs.evaluate(&Version::parser("1.0.0 Beta 1").unwrap().1));
+        assert!(!rs.evaluate(&Version::parser("2.0.0 Beta 1").unwrap().1));
+        assert!(rs.evaluate(&Version::parser("2.0.0 RC 1").unwrap().1));
+        assert!(!rs.evaluate(&Version::parser("1.0.0").unwrap().1));
+    }
+
+    {
+        let rs = parser("!($v ~ beta || $v ~ alpha)").unwrap();
+        assert!(rs.evaluate(&Version::parser("1.0.0").unwrap().1));
+        assert!(rs.evaluate(&Version::parser("1.0.0 RC 1").unwrap().1));
+        assert!(!rs.evaluate(&Version::parser("1.0.0 Beta 1").unwrap().1));
+        assert!(!rs.evaluate(&Version::parser("1.0.0 Alpha 1").unwrap().1));
+
+        let rs = parser("!!($v ~ beta)").unwrap();
+        assert!(rs.evaluate(&Version::parser("1.0.0 Beta 1").unwrap().1));
+        assert!(!rs.evaluate(&Version::parser("1.0.0").unwrap().1));
+    }
+}
+
+#[test]
+fn test_evaluate_release_date() {
+    use crate::{date::Date, version::Version};
+
+    let version = Version::parser("1.0.0").unwrap().1;
+    let released = |released| Subject {
+        version: &version,
+        released,
+    };
+
+    let rs = parser("$released < 2025-06-01").unwrap();
+    assert!(rs.evaluate(released(Some(Date::new(2025, 5, 31)))));
+    assert!(!rs.evaluate(released(Some(Date::new(2025, 6, 1)))));
+    assert!(!rs.evaluate(released(None)));
+
+    let rs = parser("$released >= 2025-06-01").unwrap();
+    assert!(!rs.evaluate(released(Some(Date::new(2025, 5, 31)))));
+    assert!(rs.evaluate(released(Some(Date::new(2025, 6, 1)))));
+    assert!(!rs.evaluate(released(None)));
+
+    let rs = parser("2025-01-01 <= $released <= 2025-06-01 && $released != 2025-03-01").unwrap();
+    assert!(rs.evaluate(released(Some(Date::new(2025, 1, 1)))));
+    assert!(!rs.evaluate(released(Some(Date::new(2025, 3, 1)))));
+    assert!(rs.evaluate(released(Some(Date::new(2025, 6, 1)))));
+    assert!(!rs.evaluate(released(Some(Date::new(2025, 6, 2)))));
+    assert!(!rs.evaluate(released(None)));
+
+    let rs = parser("$released = 2025-06-01").unwrap();
+    assert!(rs.evaluate(released(Some(Date::new(2025, 6, 1)))));
+    assert!(!rs.evaluate(released(Some(Date::new(2025, 6, 2)))));
 }
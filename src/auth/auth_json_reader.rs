@@ -16,8 +16,17 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use super::types::AuthData;
-use std::{error, fmt, path::PathBuf};
+use super::{
+    password::from_htpasswd,
+    sqlite_reader::read_auth_database,
+    types::{AuthData, GroupName, PackageName, Permissions, UserInfo},
+};
+use std::{
+    collections::HashMap,
+    error, fmt,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 type Result<T> = std::result::Result<T, AuthParseError>;
 
@@ -25,6 +34,21 @@ type Result<T> = std::result::Result<T, AuthParseError>;
 pub enum AuthParseError {
     UnableToOpen(std::io::Error),
     UnableToParse(serde_json::Error),
+    UnableToParseToml(toml::de::Error),
+    UnableToReadDatabase(rusqlite::Error),
+    InvalidDatabaseValue(String),
+    InFile(PathBuf, Box<AuthParseError>),
+    DuplicateUser(String),
+    DuplicateGroup(GroupName),
+    DuplicateNetwork(String),
+    DuplicatePackageRule(PackageName),
+    DuplicateVisibility(PackageName),
+    InvalidHtpasswdLine(usize),
+    UnknownGroup(GroupName, GroupName),
+    GroupCycle(Vec<GroupName>),
+    DuplicateToken(String, String),
+    DuplicateLicence(String, String),
+    InvalidUtf8(std::string::FromUtf8Error),
 }
 
 impl fmt::Display for AuthParseError {
@@ -33,9 +57,54 @@ impl fmt::Display for AuthParseError {
             AuthParseError::UnableToOpen(..) => {
                 write!(f, "Failed to open.")
             }
-            AuthParseError::UnableToParse(..) => {
+            AuthParseError::UnableToParse(..) | AuthParseError::UnableToParseToml(..) => {
                 write!(f, "Failed to parse.")
             }
+            AuthParseError::UnableToReadDatabase(..) => {
+                write!(f, "Failed to read the database.")
+            }
+            AuthParseError::InvalidDatabaseValue(ref value) => {
+                write!(f, "Invalid value in {value}.")
+            }
+            AuthParseError::InFile(ref path, ref err) => {
+                write!(f, "{}: {}", path.display(), err)
+            }
+            AuthParseError::DuplicateUser(ref user) => {
+                write!(f, "User “{user}” is defined more than once.")
+            }
+            AuthParseError::DuplicateGroup(ref group) => {
+                write!(f, "Group “{group}” is defined more than once.")
+            }
+            AuthParseError::DuplicateNetwork(ref network) => {
+                write!(f, "Network “{network}” is defined more than once.")
+            }
+            AuthParseError::DuplicatePackageRule(ref package) => {
+                write!(f, "Package rule “{package}” is defined more than once.")
+            }
+            AuthParseError::DuplicateVisibility(ref package) => {
+                write!(
+                    f,
+                    "The visibility of “{package}” is defined more than once."
+                )
+            }
+            AuthParseError::InvalidHtpasswdLine(line) => {
+                write!(f, "Line {line} is not a valid htpasswd entry.")
+            }
+            AuthParseError::UnknownGroup(ref group, ref unknown) => {
+                write!(f, "Group “{group}” includes the unknown group “{unknown}”.")
+            }
+            AuthParseError::GroupCycle(ref cycle) => {
+                write!(f, "Groups include each other: {}.", cycle.join(" → "))
+            }
+            AuthParseError::DuplicateToken(ref user, ref token) => {
+                write!(f, "The API token “{token}” of user “{user}” is not unique.")
+            }
+            AuthParseError::DuplicateLicence(ref user, ref licence) => {
+                write!(f, "The licence “{licence}” of user “{user}” is not unique.")
+            }
+            AuthParseError::InvalidUtf8(..) => {
+                write!(f, "Failed to parse, the file is not valid UTF-8.")
+            }
         }
     }
 }
@@ -45,6 +114,21 @@ impl error::Error for AuthParseError {
         match *self {
             AuthParseError::UnableToOpen(ref e) => Some(e),
             AuthParseError::UnableToParse(ref e) => Some(e),
+            AuthParseError::UnableToParseToml(ref e) => Some(e),
+            AuthParseError::UnableToReadDatabase(ref e) => Some(e),
+            AuthParseError::InFile(_, ref e) => Some(e.as_ref()),
+            AuthParseError::InvalidUtf8(ref e) => Some(e),
+            AuthParseError::InvalidDatabaseValue(..)
+            | AuthParseError::DuplicateUser(..)
+            | AuthParseError::DuplicateGroup(..)
+            | AuthParseError::DuplicateNetwork(..)
+            | AuthParseError::DuplicatePackageRule(..)
+            | AuthParseError::DuplicateVisibility(..)
+            | AuthParseError::InvalidHtpasswdLine(..)
+            | AuthParseError::UnknownGroup(..)
+            | AuthParseError::GroupCycle(..)
+            | AuthParseError::DuplicateToken(..)
+            | AuthParseError::DuplicateLicence(..) => None,
         }
     }
 }
@@ -55,7 +139,9 @@ impl TryFrom<std::path::PathBuf> for AuthData {
     fn try_from(path: PathBuf) -> std::result::Result<Self, Self::Error> {
         let file = std::fs::File::open(path).map_err(AuthParseError::UnableToOpen)?;
 
-        serde_json::from_reader(file).map_err(AuthParseError::UnableToParse)
+        serde_json::from_reader(file)
+            .map_err(AuthParseError::UnableToParse)
+            .and_then(validate)
     }
 }
 
@@ -63,7 +149,9 @@ impl TryFrom<&[u8]> for AuthData {
     type Error = AuthParseError;
 
     fn try_from(slice: &[u8]) -> std::result::Result<Self, Self::Error> {
-        serde_json::from_slice(slice).map_err(AuthParseError::UnableToParse)
+        serde_json::from_slice(slice)
+            .map_err(AuthParseError::UnableToParse)
+            .and_then(validate)
     }
 }
 
@@ -71,12 +159,313 @@ impl TryFrom<&str> for AuthData {
     type Error = AuthParseError;
 
     fn try_from(str: &str) -> std::result::Result<Self, Self::Error> {
-        serde_json::from_str(str).map_err(AuthParseError::UnableToParse)
+        serde_json::from_str(str)
+            .map_err(AuthParseError::UnableToParse)
+            .and_then(validate)
+    }
+}
+
+/// Ensures that all included groups exist, that no group includes itself
+/// and that every API token and every licence belongs to exactly one user.
+///
+/// Builds the indexes of the API tokens and of the licences.
+fn validate(mut auth_data: AuthData) -> Result<AuthData> {
+    #[derive(PartialEq)]
+    enum State {
+        Visiting,
+        Done,
+    }
+
+    fn visit<'a>(
+        auth_data: &'a AuthData,
+        name: &'a str,
+        states: &mut HashMap<&'a str, State>,
+        path: &mut Vec<&'a str>,
+    ) -> Result<()> {
+        match states.get(name) {
+            Some(State::Done) => return Ok(()),
+            Some(State::Visiting) => {
+                let start = path.iter().position(|group| *group == name).unwrap();
+                let mut cycle = path[start..]
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>();
+                cycle.push(name.to_owned());
+
+                return Err(AuthParseError::GroupCycle(cycle));
+            }
+            None => (),
+        }
+
+        states.insert(name, State::Visiting);
+        path.push(name);
+
+        for included in &auth_data.groups[name].groups {
+            if !auth_data.groups.contains_key(included) {
+                return Err(AuthParseError::UnknownGroup(
+                    name.to_owned(),
+                    included.clone(),
+                ));
+            }
+
+            visit(auth_data, included, states, path)?;
+        }
+
+        path.pop();
+        states.insert(name, State::Done);
+
+        Ok(())
+    }
+
+    let mut names = auth_data.groups.keys().collect::<Vec<_>>();
+    names.sort();
+
+    let mut states = HashMap::new();
+
+    for name in names {
+        visit(&auth_data, name, &mut states, &mut Vec::new())?;
+    }
+
+    let mut usernames = auth_data.users.keys().collect::<Vec<_>>();
+    usernames.sort();
+
+    let mut digests = HashMap::new();
+    let mut licences = HashMap::new();
+
+    for username in usernames {
+        let mut tokens = auth_data.users[username].tokens.iter().collect::<Vec<_>>();
+        tokens.sort_by_key(|(name, _)| *name);
+
+        for (name, token) in tokens {
+            if digests
+                .insert(token.sha256, (username.clone(), name.clone()))
+                .is_some()
+            {
+                return Err(AuthParseError::DuplicateToken(
+                    username.clone(),
+                    name.clone(),
+                ));
+            }
+        }
+
+        let mut numbers = auth_data.users[username]
+            .licences
+            .keys()
+            .collect::<Vec<_>>();
+        numbers.sort();
+
+        for number in numbers {
+            if licences.insert(number.clone(), username.clone()).is_some() {
+                return Err(AuthParseError::DuplicateLicence(
+                    username.clone(),
+                    number.clone(),
+                ));
+            }
+        }
+    }
+
+    static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+    auth_data.tokens = digests;
+    auth_data.licences = licences;
+    auth_data.generation = GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
+
+    Ok(auth_data)
+}
+
+/// Parses a single file of auth data, either JSON or TOML depending on its extension.
+///
+/// The data is not validated, as it may refer to groups of other files.
+fn read_fragment(path: &Path) -> Result<AuthData> {
+    let contents = std::fs::read(path).map_err(AuthParseError::UnableToOpen)?;
+
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "toml")
+    {
+        let contents = String::from_utf8(contents).map_err(AuthParseError::InvalidUtf8)?;
+
+        toml::from_str(&contents).map_err(AuthParseError::UnableToParseToml)
+    } else {
+        serde_json::from_slice(&contents).map_err(AuthParseError::UnableToParse)
+    }
+}
+
+/// Returns the fragments of the `auth.d` directory in the order they are merged.
+///
+/// Only `.json` and `.toml` files are considered, hidden files are ignored.
+fn fragments(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+
+    for entry in dir.read_dir().map_err(AuthParseError::UnableToOpen)? {
+        let path = entry.map_err(AuthParseError::UnableToOpen)?.path();
+
+        let is_hidden = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        let is_fragment = path
+            .extension()
+            .is_some_and(|extension| extension == "json" || extension == "toml");
+
+        if is_fragment && !is_hidden && path.is_file() {
+            paths.push(path);
+        }
+    }
+
+    paths.sort();
+
+    Ok(paths)
+}
+
+fn merge_permissions(target: &mut Permissions, source: Permissions) -> Result<()> {
+    for (rules, source) in [
+        (&mut target.allow, source.allow),
+        (&mut target.deny, source.deny),
+    ] {
+        for (package, ruleset) in source {
+            if rules.contains_key(&package) {
+                return Err(AuthParseError::DuplicatePackageRule(package));
+            }
+
+            rules.insert(package, ruleset);
+        }
+    }
+
+    Ok(())
+}
+
+/// Merges `source` into `target`, users, groups, networks and the visibility
+/// of a package pattern may only be defined once.
+fn merge(target: &mut AuthData, source: AuthData) -> Result<()> {
+    for (name, user) in source.users {
+        if target.users.contains_key(&name) {
+            return Err(AuthParseError::DuplicateUser(name));
+        }
+
+        target.users.insert(name, user);
+    }
+
+    for (name, group) in source.groups {
+        if target.groups.contains_key(&name) {
+            return Err(AuthParseError::DuplicateGroup(name));
+        }
+
+        target.groups.insert(name, group);
+    }
+
+    for (name, network) in source.networks {
+        if target.networks.contains_key(&name) {
+            return Err(AuthParseError::DuplicateNetwork(name));
+        }
+
+        target.networks.insert(name, network);
+    }
+
+    for (package, visibility) in source.visibility {
+        if target
+            .visibility
+            .iter()
+            .any(|(pattern, _)| *pattern == package)
+        {
+            return Err(AuthParseError::DuplicateVisibility(package));
+        }
+
+        target.visibility.push((package, visibility));
+    }
+
+    target.htpasswd.extend(source.htpasswd);
+
+    merge_permissions(&mut target.packages, source.packages)
+}
+
+/// Adds the users of an Apache htpasswd file, users may only be defined once.
+fn import_htpasswd(target: &mut AuthData, index: usize) -> Result<()> {
+    let file = &target.htpasswd[index];
+    let contents = std::fs::read_to_string(&file.path).map_err(AuthParseError::UnableToOpen)?;
+    let mut users = Vec::new();
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match line.split_once(':') {
+            Some((name, hash)) if !name.is_empty() => {
+                let user = UserInfo {
+                    passwd: from_htpasswd(hash),
+                    valid_until: None,
+                    groups: file.groups.clone(),
+                    packages: Permissions::default(),
+                    tokens: HashMap::new(),
+                    licences: HashMap::new(),
+                    download_quota: None,
+                };
+
+                users.push((name.to_owned(), user));
+            }
+            _ => return Err(AuthParseError::InvalidHtpasswdLine(number + 1)),
+        }
+    }
+
+    for (name, user) in users {
+        if target.users.contains_key(&name) {
+            return Err(AuthParseError::DuplicateUser(name));
+        }
+
+        target.users.insert(name, user);
     }
+
+    Ok(())
 }
 
-pub fn read_auth_json(path: std::path::PathBuf) -> Result<AuthData> {
-    AuthData::try_from(path)
+/// Reads the auth data of the package directory: `auth.json`, all
+/// fragments within the `auth.d` directory and the `auth.sqlite`
+/// database, all of which are optional.
+///
+/// `auth.json` is read first, followed by the fragments in the order of
+/// their file names, the database and finally the htpasswd files they
+/// reference. A user, a group, a network or a global package rule must
+/// only be defined in one of them.
+pub fn read_auth_data(package_dir: &Path) -> Result<AuthData> {
+    let mut paths = Vec::new();
+
+    let auth_json = package_dir.join("auth.json");
+    if auth_json.exists() {
+        paths.push(auth_json);
+    }
+
+    let auth_d = package_dir.join("auth.d");
+    if auth_d.is_dir() {
+        paths.extend(fragments(&auth_d)?);
+    }
+
+    let mut auth_data = AuthData::default();
+
+    for path in paths {
+        read_fragment(&path)
+            .and_then(|fragment| merge(&mut auth_data, fragment))
+            .map_err(|err| AuthParseError::InFile(path, Box::new(err)))?;
+    }
+
+    let auth_sqlite = package_dir.join("auth.sqlite");
+    if auth_sqlite.exists() {
+        read_auth_database(&auth_sqlite)
+            .and_then(|database| merge(&mut auth_data, database))
+            .map_err(|err| AuthParseError::InFile(auth_sqlite, Box::new(err)))?;
+    }
+
+    for index in 0..auth_data.htpasswd.len() {
+        let file = &mut auth_data.htpasswd[index];
+        file.path = package_dir.join(&file.path);
+
+        import_htpasswd(&mut auth_data, index).map_err(|err| {
+            AuthParseError::InFile(auth_data.htpasswd[index].path.clone(), Box::new(err))
+        })?;
+    }
+
+    validate(auth_data)
 }
 
 #[test]
@@ -92,7 +481,7 @@ fn test_deserialize() {
 fn test_read_auth_json() {
     let file = std::path::PathBuf::from("packages/auth.json.example");
 
-    assert!(read_auth_json(file).is_ok());
+    assert!(AuthData::try_from(file).is_ok());
 }
 
 #[test]
@@ -101,7 +490,7 @@ fn test_parse() {
         let data = AuthData::try_from(r#"{}"#).unwrap();
         assert_eq!(data.users.len(), 0);
         assert_eq!(data.groups.len(), 0);
-        assert_eq!(data.packages.len(), 0);
+        assert!(data.packages.is_empty());
     }
 
     {
@@ -115,7 +504,7 @@ fn test_parse() {
         .unwrap();
         assert_eq!(data.users.len(), 0);
         assert_eq!(data.groups.len(), 0);
-        assert_eq!(data.packages.len(), 0);
+        assert!(data.packages.is_empty());
     }
 
     {
@@ -151,7 +540,7 @@ fn test_parse() {
         assert!(data.users.get("root").unwrap().passwd.verify("root"));
 
         assert_eq!(data.groups.len(), 0);
-        assert_eq!(data.packages.len(), 0);
+        assert!(data.packages.is_empty());
     }
 
     {
@@ -164,11 +553,432 @@ fn test_parse() {
         )
         .unwrap();
 
-        assert_eq!(data.packages.len(), 1);
+        assert_eq!(data.packages.allow.len(), 1);
+        assert_eq!(data.packages.deny.len(), 0);
 
-        let package = data.packages.keys().next().unwrap();
+        let package = data.packages.allow.keys().next().unwrap();
 
         assert!(package.0.is_match("foo"));
         assert!(!package.0.is_match("foobar"));
     }
+
+    {
+        let data = AuthData::try_from(
+            r#"{
+            "packages": {
+                "foo.*": "*",
+                "!foo.bar": "$v ~ beta"
+            }
+        }"#,
+        )
+        .unwrap();
+
+        assert_eq!(data.packages.allow.len(), 1);
+        assert_eq!(data.packages.deny.len(), 1);
+
+        let package = data.packages.deny.keys().next().unwrap();
+
+        assert!(package.0.is_match("foo.bar"));
+        assert!(!package.0.is_match("!foo.bar"));
+    }
+}
+
+#[test]
+fn test_nested_groups() {
+    {
+        let data = AuthData::try_from(
+            r#"{
+            "groups": {
+                "Basic": {
+                    "be.bastelstu.basic": "*"
+                },
+                "Premium": {
+                    "groups": [ "Basic" ],
+                    "packages": {
+                        "be.bastelstu.premium": "*"
+                    }
+                },
+                "Enterprise": {
+                    "groups": [ "Premium", "Basic" ]
+                }
+            }
+        }"#,
+        )
+        .unwrap();
+
+        assert_eq!(data.groups.len(), 3);
+        assert_eq!(data.groups["Basic"].groups.len(), 0);
+        assert_eq!(data.groups["Basic"].packages.allow.len(), 1);
+        assert_eq!(data.groups["Premium"].groups, vec!["Basic"]);
+        assert_eq!(data.groups["Premium"].packages.allow.len(), 1);
+        assert!(data.groups["Enterprise"].packages.is_empty());
+
+        assert_eq!(data.resolve_group("Basic").len(), 1);
+        assert_eq!(data.resolve_group("Premium").len(), 2);
+        assert_eq!(data.resolve_group("Enterprise").len(), 3);
+        assert_eq!(data.resolve_group("Unknown").len(), 0);
+    }
+
+    {
+        let err = AuthData::try_from(
+            r#"{
+            "groups": {
+                "A": { "groups": [ "B" ] },
+                "B": { "groups": [ "C" ] },
+                "C": { "groups": [ "A" ] }
+            }
+        }"#,
+        )
+        .unwrap_err();
+
+        assert!(
+            matches!(err, AuthParseError::GroupCycle(ref cycle) if cycle == &["A", "B", "C", "A"])
+        );
+    }
+
+    {
+        let err =
+            AuthData::try_from(r#"{ "groups": { "A": { "groups": [ "A" ] } } }"#).unwrap_err();
+
+        assert!(matches!(err, AuthParseError::GroupCycle(ref cycle) if cycle == &["A", "A"]));
+    }
+
+    {
+        let err =
+            AuthData::try_from(r#"{ "groups": { "A": { "groups": [ "B" ] } } }"#).unwrap_err();
+
+        assert!(
+            matches!(err, AuthParseError::UnknownGroup(ref group, ref unknown) if group == "A" && unknown == "B")
+        );
+    }
+
+    assert!(AuthData::try_from(
+        r#"{ "groups": { "A": { "groups": [ ], "be.bastelstu.basic": "*" } } }"#
+    )
+    .is_err());
+}
+
+#[test]
+fn test_download_quota() {
+    use super::types::DownloadQuota;
+
+    let data = AuthData::try_from(
+        r#"{
+        "users": {
+            "Customer": {
+                "passwd": "-",
+                "groups": [ "Basic", "Premium" ]
+            },
+            "Reseller": {
+                "passwd": "-",
+                "groups": [ "Premium" ],
+                "download_quota": { "downloads": 1000, "window": 86400 }
+            },
+            "Staff": {
+                "passwd": "-"
+            }
+        },
+        "groups": {
+            "Basic": {
+                "be.bastelstu.basic": "*"
+            },
+            "Premium": {
+                "groups": [ "Limited" ],
+                "packages": {
+                    "be.bastelstu.premium": "*"
+                }
+            },
+            "Limited": {
+                "download_quota": { "downloads": 10, "window": 3600 }
+            }
+        }
+    }"#,
+    )
+    .unwrap();
+
+    let quota = |user: &str| data.download_quota(&data.users[user]);
+
+    assert_eq!(
+        quota("Customer"),
+        Some(DownloadQuota {
+            downloads: 10,
+            window: 3600
+        })
+    );
+    assert_eq!(
+        quota("Reseller"),
+        Some(DownloadQuota {
+            downloads: 1000,
+            window: 86400
+        })
+    );
+    assert_eq!(quota("Staff"), None);
+
+    // The quota may not be mixed with plain package rules
+    assert!(AuthData::try_from(
+        r#"{
+        "groups": {
+            "Basic": {
+                "be.bastelstu.basic": "*",
+                "download_quota": { "downloads": 10, "window": 3600 }
+            }
+        }
+    }"#,
+    )
+    .is_err());
+
+    let data = AuthData::try_from(
+        r#"{
+        "groups": {
+            "Basic": {
+                "packages": {
+                    "be.bastelstu.basic": "*"
+                },
+                "download_quota": { "downloads": 10, "window": 3600 }
+            }
+        }
+    }"#,
+    )
+    .unwrap();
+
+    assert_eq!(data.groups["Basic"].packages.allow.len(), 1);
+    assert!(data.groups["Basic"].download_quota.is_some());
+}
+
+#[test]
+fn test_duplicate_licence() {
+    let err = AuthData::try_from(
+        r#"{
+        "users": {
+            "A": {
+                "passwd": "-",
+                "licences": { "123456": { "serial_key": "-" } }
+            },
+            "B": {
+                "passwd": "-",
+                "licences": { "123456": { "serial_key": "-" } }
+            }
+        }
+    }"#,
+    )
+    .unwrap_err();
+
+    assert!(
+        matches!(err, AuthParseError::DuplicateLicence(ref user, ref licence) if user == "B" && licence == "123456")
+    );
+}
+
+#[test]
+fn test_api_tokens() {
+    let data = AuthData::try_from(
+        r#"{
+        "users": {
+            "test": {
+                "passwd": "-",
+                "tokens": {
+                    "ci": {
+                        "sha256": "9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08",
+                        "expires": "2025-06-01",
+                        "packages": [ "be.bastelstu.*" ]
+                    }
+                }
+            }
+        }
+    }"#,
+    )
+    .unwrap();
+    let token = &data.users["test"].tokens["ci"];
+    assert_eq!(token.expires, Some(crate::date::Date::new(2025, 6, 1)));
+    assert!(!token.revoked);
+    assert!(token.is_in_scope("be.bastelstu.test"));
+    assert!(!token.is_in_scope("com.example.test"));
+
+    assert!(AuthData::try_from(
+        r#"{ "users": { "test": { "passwd": "-", "tokens": { "ci": { "sha256": "9f86d0" } } } } }"#
+    )
+    .is_err());
+    assert!(AuthData::try_from(
+        r#"{ "users": { "test": { "passwd": "-", "tokens": { "ci": { "sha256": "xf86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08" } } } } }"#
+    )
+    .is_err());
+
+    let duplicate = AuthData::try_from(
+        r#"{
+        "users": {
+            "a": {
+                "passwd": "-",
+                "tokens": { "ci": { "sha256": "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08" } }
+            },
+            "b": {
+                "passwd": "-",
+                "tokens": { "ci": { "sha256": "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08" } }
+            }
+        }
+    }"#,
+    );
+    assert!(matches!(
+        duplicate,
+        Err(AuthParseError::DuplicateToken(ref user, ref token)) if user == "b" && token == "ci"
+    ));
+}
+
+#[test]
+fn test_read_auth_data() {
+    let dir = std::env::temp_dir().join(format!("auth-d-test-{}", std::process::id()));
+    let auth_d = dir.join("auth.d");
+    std::fs::create_dir_all(&auth_d).unwrap();
+
+    std::fs::write(
+        dir.join("auth.json"),
+        r#"{
+            "users": { "admin": { "passwd": "-", "groups": [ "Premium" ] } },
+            "packages": { "be.bastelstu.free": "*" }
+        }"#,
+    )
+    .unwrap();
+    std::fs::write(
+        auth_d.join("10-groups.toml"),
+        r#"
+            [groups.Premium]
+            groups = [ "Basic" ]
+            packages = { "be.bastelstu.premium" = "*" }
+
+            [groups.Basic]
+            "be.bastelstu.basic" = "$v !~ dev"
+        "#,
+    )
+    .unwrap();
+    std::fs::write(
+        auth_d.join("20-customers.json"),
+        r#"{ "users": { "customer": { "passwd": "-", "groups": [ "Basic" ] } } }"#,
+    )
+    .unwrap();
+    std::fs::write(auth_d.join("README.md"), "Not a fragment").unwrap();
+    std::fs::write(auth_d.join(".20-customers.json.swp"), "Not a fragment").unwrap();
+
+    let data = read_auth_data(&dir).unwrap();
+    assert_eq!(data.users.len(), 2);
+    assert_eq!(data.groups.len(), 2);
+    assert_eq!(data.groups["Premium"].groups, vec!["Basic".to_owned()]);
+    assert_eq!(data.packages.allow.len(), 1);
+
+    // Users may only be defined once
+    std::fs::write(
+        auth_d.join("30-admin.json"),
+        r#"{ "users": { "admin": { "passwd": "-" } } }"#,
+    )
+    .unwrap();
+    match read_auth_data(&dir) {
+        Err(AuthParseError::InFile(path, err)) => {
+            assert_eq!(path, auth_d.join("30-admin.json"));
+            assert!(matches!(*err, AuthParseError::DuplicateUser(ref user) if user == "admin"));
+        }
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    // Global package rules may only be defined once
+    std::fs::write(
+        auth_d.join("30-admin.json"),
+        r#"{ "packages": { "be.bastelstu.free": "$v ~ beta" } }"#,
+    )
+    .unwrap();
+    match read_auth_data(&dir) {
+        Err(AuthParseError::InFile(_, err)) => {
+            assert_eq!(
+                err.to_string(),
+                "Package rule “be.bastelstu.free” is defined more than once."
+            );
+        }
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    // Included groups must be defined in any of the files
+    std::fs::write(
+        auth_d.join("30-admin.json"),
+        r#"{ "groups": { "Admin": { "groups": [ "Unknown" ], "packages": {} } } }"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        read_auth_data(&dir),
+        Err(AuthParseError::UnknownGroup(..))
+    ));
+    std::fs::remove_file(auth_d.join("30-admin.json")).unwrap();
+
+    // Fragments must be valid UTF-8
+    std::fs::write(
+        auth_d.join("30-admin.toml"),
+        b"[users.admin2]\npasswd = \"\xff\"\n",
+    )
+    .unwrap();
+    match read_auth_data(&dir) {
+        Err(AuthParseError::InFile(path, err)) => {
+            assert_eq!(path, auth_d.join("30-admin.toml"));
+            assert!(matches!(*err, AuthParseError::InvalidUtf8(..)));
+        }
+        result => panic!("Unexpected result: {:?}", result),
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // Neither `auth.json` nor `auth.d` are required
+    let data = read_auth_data(&dir).unwrap();
+    assert!(data.users.is_empty());
+}
+
+#[test]
+fn test_read_htpasswd() {
+    let dir = std::env::temp_dir().join(format!("htpasswd-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    std::fs::write(
+        dir.join("auth.json"),
+        r#"{
+            "users": { "admin": { "passwd": "-" } },
+            "groups": { "Customer": { "be.bastelstu.premium": "*" } },
+            "htpasswd": [ "staff.htpasswd", { "path": "customers.htpasswd", "groups": [ "Customer" ] } ]
+        }"#,
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("staff.htpasswd"),
+        "# Staff\nbcrypt:$2y$08$n1CcYF1ABM8gNMjJqi8Z4uHEL1OYQLPUM05sXaEuoOiRrJHxq6yt2\n\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("customers.htpasswd"),
+        "apr1:$apr1$saltsalt$NBMPZFGR7yazCdGvlWJji1\nsha1:{SHA}qUqP5cyxm6YcTAhz05Hph5gvu9M=\n",
+    )
+    .unwrap();
+
+    let data = read_auth_data(&dir).unwrap();
+    assert_eq!(data.users.len(), 4);
+    assert!(data.users["bcrypt"].passwd.verify("test"));
+    assert!(data.users["bcrypt"].groups.is_empty());
+    assert!(data.users["apr1"].passwd.verify("test"));
+    assert!(!data.users["apr1"].passwd.verify("root"));
+    assert!(data.users["sha1"].passwd.verify("test"));
+    assert_eq!(data.users["sha1"].groups[0].name, "Customer");
+    assert_eq!(data.htpasswd[0].path, dir.join("staff.htpasswd"));
+
+    // Users may only be defined once
+    std::fs::write(
+        dir.join("staff.htpasswd"),
+        "admin:$apr1$saltsalt$NBMPZFGR7yazCdGvlWJji1\n",
+    )
+    .unwrap();
+    assert_eq!(
+        read_auth_data(&dir).unwrap_err().to_string(),
+        format!(
+            "{}: User “admin” is defined more than once.",
+            dir.join("staff.htpasswd").display()
+        )
+    );
+
+    std::fs::write(dir.join("staff.htpasswd"), "admin\n").unwrap();
+    assert!(matches!(
+        read_auth_data(&dir),
+        Err(AuthParseError::InFile(_, err)) if matches!(*err, AuthParseError::InvalidHtpasswdLine(1))
+    ));
+
+    std::fs::remove_dir_all(&dir).unwrap();
 }
@@ -0,0 +1,107 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use super::{Authenticator, Credentials};
+use crate::{
+    auth::{AuthData, PasswordHash, TokenDigest},
+    date::Date,
+    http::helpers::AuthInfo,
+    SETTINGS,
+};
+use futures_util::future::{ready, FutureExt, LocalBoxFuture};
+use std::time::{Duration, SystemTime};
+
+/// Authenticates the users, API tokens and licences of the auth data.
+///
+/// Licences are authenticated with their licence number as the username and
+/// their serial key as the password, as sent by WoltLab Suite to commercial
+/// package servers. A user whose name equals the licence number takes precedence.
+#[derive(Debug)]
+pub struct AuthJson;
+
+impl AuthJson {
+    fn resolve(auth_data: &AuthData, credentials: Credentials) -> Option<AuthInfo> {
+        let (username, password) = match credentials {
+            Credentials::Password { username, password } => (Some(username), password),
+            Credentials::Token(token) => (None, token),
+        };
+
+        let today = Date::from(SystemTime::now());
+        let token = auth_data
+            .find_token(&TokenDigest::of(password))
+            .filter(|(user, _, token)| {
+                username.is_none_or(|username| username == *user) && token.is_valid(today)
+            });
+
+        if let Some((username, name, _)) = token {
+            return Some(AuthInfo {
+                username: Some(username.to_owned()),
+                token: Some(name.to_owned()),
+                licence: None,
+                user: None,
+                ip: None,
+            });
+        }
+
+        let username = username?;
+        let verify = |key: &str, hash: &dyn PasswordHash| {
+            auth_data.credential_cache.verify(
+                key,
+                password,
+                Duration::from_secs(SETTINGS.credential_cache_ttl),
+                SETTINGS.credential_cache_size,
+                || hash.verify(password),
+            )
+        };
+
+        if let Some(user) = auth_data.users.get(username) {
+            if verify(username, user.passwd.as_ref()) {
+                return Some(AuthInfo {
+                    username: Some(username.to_owned()),
+                    token: None,
+                    licence: None,
+                    user: None,
+                    ip: None,
+                });
+            }
+        }
+
+        // Usernames of basic authentication never contain a colon, thus
+        // the cache entries of licences cannot be mistaken for users.
+        auth_data
+            .find_licence(username)
+            .filter(|(_, licence)| verify(&format!(":{username}"), licence.serial_key.as_ref()))
+            .map(|(user, _)| AuthInfo {
+                username: Some(user.to_owned()),
+                token: None,
+                licence: Some(username.to_owned()),
+                user: None,
+                ip: None,
+            })
+    }
+}
+
+impl Authenticator for AuthJson {
+    fn authenticate<'a>(
+        &'a self,
+        auth_data: &'a AuthData,
+        credentials: Credentials<'a>,
+    ) -> LocalBoxFuture<'a, crate::Result<Option<AuthInfo>>> {
+        ready(Ok(AuthJson::resolve(auth_data, credentials))).boxed_local()
+    }
+}
@@ -0,0 +1,331 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use super::{AuthJson, Authenticator, Credentials};
+use crate::{
+    auth::{
+        credential_cache::CredentialCache, hashers::BannedUser, AuthData, DownloadQuota,
+        GroupMembership, Permissions, UserInfo,
+    },
+    date::Date,
+    http::helpers::AuthInfo,
+};
+use actix_web::http::StatusCode;
+use futures_util::future::{FutureExt, LocalBoxFuture};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use url::Url;
+
+/// The request body sent to the callback.
+#[derive(Debug, Serialize)]
+struct CallbackRequest<'a> {
+    /// `None` if the request carries an API token on its own.
+    username: Option<&'a str>,
+    password: &'a str,
+}
+
+/// The response body of the callback for valid credentials.
+#[derive(Debug, Deserialize)]
+struct CallbackResponse {
+    /// The user the credentials belong to, required for API tokens.
+    username: Option<String>,
+
+    #[serde(default)]
+    valid_until: Option<Date>,
+
+    #[serde(default)]
+    groups: Vec<GroupMembership>,
+
+    #[serde(default)]
+    packages: Permissions,
+
+    #[serde(default)]
+    download_quota: Option<DownloadQuota>,
+}
+
+/// Authenticates users by POSTing their credentials as JSON to an HTTP endpoint.
+///
+/// The endpoint responds with `200 OK` and the user’s `groups` and `packages`
+/// (in the format of `auth.json`) if the credentials are valid, and with
+/// `401 Unauthorized`, `403 Forbidden` or `404 Not Found` otherwise:
+///
+/// ```text
+/// → { "username": "Customer", "password": "secret" }
+/// ← { "groups": [ "Premium" ], "packages": { "be.bastelstu.example": "*" } }
+/// ```
+///
+/// Groups are looked up in the auth data. Users and API tokens of the auth
+/// data are authenticated locally, without asking the endpoint.
+pub struct HttpCallback {
+    url: Url,
+    timeout: Duration,
+    cache_ttl: Duration,
+    cache_size: usize,
+    /// The user of the credentials and the `AuthData::generation` it was resolved with.
+    cache: CredentialCache<(u64, String, Arc<UserInfo>)>,
+}
+
+impl HttpCallback {
+    pub fn new(url: Url, timeout: Duration, cache_ttl: Duration, cache_size: usize) -> Self {
+        Self {
+            url,
+            timeout,
+            cache_ttl,
+            cache_size,
+            cache: CredentialCache::new(),
+        }
+    }
+
+    async fn request(
+        &self,
+        request: &CallbackRequest<'_>,
+    ) -> crate::Result<Option<(String, UserInfo)>> {
+        thread_local! {
+            /// Clients cannot be shared across threads, thus every worker
+            /// creates its own one on the first request.
+            static CLIENT: awc::Client = awc::Client::default();
+        }
+
+        let mut response = CLIENT
+            .with(|client| client.post(self.url.as_str()))
+            .timeout(self.timeout)
+            .send_json(request)
+            .await
+            .map_err(|err| err.to_string())?;
+
+        match response.status() {
+            StatusCode::OK => (),
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::NOT_FOUND => {
+                return Ok(None);
+            }
+            status => return Err(format!("Unexpected status code: {}", status).into()),
+        }
+
+        let body = response
+            .json::<CallbackResponse>()
+            .await
+            .map_err(|err| err.to_string())?;
+
+        let username = match (body.username, request.username) {
+            (Some(username), _) => username,
+            (None, Some(username)) => username.to_owned(),
+            (None, None) => return Err("The response for an API token lacks the username".into()),
+        };
+
+        let user = UserInfo {
+            passwd: Box::new(BannedUser),
+            valid_until: body.valid_until,
+            groups: body.groups,
+            packages: body.packages,
+            tokens: HashMap::new(),
+            licences: HashMap::new(),
+            download_quota: body.download_quota,
+        };
+
+        Ok(Some((username, user)))
+    }
+
+    async fn authenticate(
+        &self,
+        auth_data: &AuthData,
+        credentials: Credentials<'_>,
+    ) -> crate::Result<Option<AuthInfo>> {
+        if let Some(auth_info) = AuthJson.authenticate(auth_data, credentials).await? {
+            return Ok(Some(auth_info));
+        }
+
+        let request = match credentials {
+            Credentials::Password { username, .. } if auth_data.users.contains_key(username) => {
+                return Ok(None);
+            }
+            Credentials::Password { username, password } => CallbackRequest {
+                username: Some(username),
+                password,
+            },
+            Credentials::Token(token) => CallbackRequest {
+                username: None,
+                password: token,
+            },
+        };
+
+        // The cache outlives the auth data, its entries must not keep the
+        // groups and permissions of the previously loaded auth data
+        let cache_username = request.username.unwrap_or_default();
+        let cached = self
+            .cache
+            .get(cache_username, request.password, self.cache_ttl)
+            .filter(|(generation, ..)| *generation == auth_data.generation);
+
+        let (_, username, user) = match cached {
+            Some(cached) => cached,
+            None => match self.request(&request).await? {
+                Some((username, user)) => {
+                    let value = (auth_data.generation, username, Arc::new(user));
+
+                    self.cache.insert(
+                        cache_username,
+                        request.password,
+                        value.clone(),
+                        self.cache_ttl,
+                        self.cache_size,
+                    );
+
+                    value
+                }
+                None => return Ok(None),
+            },
+        };
+
+        Ok(Some(AuthInfo {
+            username: Some(username),
+            token: None,
+            licence: None,
+            user: Some(user),
+            ip: None,
+        }))
+    }
+}
+
+impl Authenticator for HttpCallback {
+    fn authenticate<'a>(
+        &'a self,
+        auth_data: &'a AuthData,
+        credentials: Credentials<'a>,
+    ) -> LocalBoxFuture<'a, crate::Result<Option<AuthInfo>>> {
+        HttpCallback::authenticate(self, auth_data, credentials).boxed_local()
+    }
+}
+
+#[actix_web::test]
+async fn test_authenticate() {
+    use actix_web::{web, App, HttpResponse, HttpServer};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static REQUESTS: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Deserialize)]
+    struct Request {
+        username: Option<String>,
+        password: String,
+    }
+
+    async fn stub(request: web::Json<Request>) -> HttpResponse {
+        REQUESTS.fetch_add(1, Ordering::SeqCst);
+
+        let body = match (request.username.as_deref(), request.password.as_str()) {
+            (Some("Customer"), "secret") => {
+                r#"{ "groups": [ "Premium" ], "packages": { "be.bastelstu.user": "*" } }"#
+            }
+            (None, "token") => r#"{ "username": "Customer" }"#,
+            (None, "anonymous") => r#"{}"#,
+            (Some("Broken"), _) => return HttpResponse::InternalServerError().finish(),
+            _ => return HttpResponse::Unauthorized().finish(),
+        };
+
+        HttpResponse::Ok()
+            .content_type("application/json")
+            .body(body)
+    }
+
+    let server = HttpServer::new(|| App::new().route("/auth", web::post().to(stub)))
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+    let url = Url::parse(&format!("http://{}/auth", server.addrs()[0])).unwrap();
+    let server = server.run();
+    let handle = server.handle();
+    actix_web::rt::spawn(server);
+
+    let auth_json = r#"{
+        "users": {
+            "admin": {
+                "passwd": "Bcrypt:$2y$08$n1CcYF1ABM8gNMjJqi8Z4uHEL1OYQLPUM05sXaEuoOiRrJHxq6yt2"
+            }
+        },
+        "groups": {
+            "Premium": {
+                "be.bastelstu.premium": "*"
+            }
+        }
+    }"#;
+    let auth_data = AuthData::try_from(auth_json).unwrap();
+
+    let callback = HttpCallback::new(url, Duration::from_secs(5), Duration::from_secs(60), 16);
+    let authenticate = |username, password| {
+        let credentials = match username {
+            Some(username) => Credentials::Password { username, password },
+            None => Credentials::Token(password),
+        };
+
+        callback.authenticate(&auth_data, credentials)
+    };
+
+    let auth_info = authenticate(Some("Customer"), "secret")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(auth_info.username.as_deref(), Some("Customer"));
+    let user = auth_info.user(&auth_data).unwrap();
+    assert_eq!(user.groups[0].name, "Premium");
+    assert!(!user.packages.is_empty());
+    assert_eq!(REQUESTS.load(Ordering::SeqCst), 1);
+
+    // Successful responses are cached
+    assert!(authenticate(Some("Customer"), "secret")
+        .await
+        .unwrap()
+        .is_some());
+    assert_eq!(REQUESTS.load(Ordering::SeqCst), 1);
+
+    // Unless the auth data was reloaded since
+    let reloaded = AuthData::try_from(auth_json).unwrap();
+    let credentials = Credentials::Password {
+        username: "Customer",
+        password: "secret",
+    };
+    assert!(callback
+        .authenticate(&reloaded, credentials)
+        .await
+        .unwrap()
+        .is_some());
+    assert_eq!(REQUESTS.load(Ordering::SeqCst), 2);
+
+    assert!(authenticate(Some("Customer"), "wrong")
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(REQUESTS.load(Ordering::SeqCst), 3);
+
+    let auth_info = authenticate(None, "token").await.unwrap().unwrap();
+    assert_eq!(auth_info.username.as_deref(), Some("Customer"));
+    assert!(authenticate(None, "anonymous").await.is_err());
+    assert!(authenticate(Some("Broken"), "secret").await.is_err());
+    assert_eq!(REQUESTS.load(Ordering::SeqCst), 6);
+
+    // Users of the auth data are authenticated locally
+    let auth_info = authenticate(Some("admin"), "test").await.unwrap().unwrap();
+    assert!(auth_info.user.is_none());
+    assert!(authenticate(Some("admin"), "secret")
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(REQUESTS.load(Ordering::SeqCst), 6);
+
+    // The client keeps its connection alive, which a graceful stop would wait for
+    handle.stop(false).await;
+}
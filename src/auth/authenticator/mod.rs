@@ -0,0 +1,64 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+mod auth_json;
+mod http_callback;
+
+pub use auth_json::*;
+pub use http_callback::*;
+
+use super::AuthData;
+use crate::{http::helpers::AuthInfo, SETTINGS};
+use futures_util::future::LocalBoxFuture;
+use std::time::Duration;
+
+/// The credentials of a request.
+#[derive(Debug, Clone, Copy)]
+pub enum Credentials<'a> {
+    /// A username and a password, the password may also be one of the user’s API tokens.
+    Password {
+        username: &'a str,
+        password: &'a str,
+    },
+
+    /// An API token on its own.
+    Token(&'a str),
+}
+
+/// Resolves the credentials of a request to a user.
+pub trait Authenticator: Send + Sync {
+    /// Returns the authenticated user, or `None` if the credentials are invalid.
+    fn authenticate<'a>(
+        &'a self,
+        auth_data: &'a AuthData,
+        credentials: Credentials<'a>,
+    ) -> LocalBoxFuture<'a, crate::Result<Option<AuthInfo>>>;
+}
+
+/// Returns the authenticator configured in the settings.
+pub fn from_settings() -> Box<dyn Authenticator> {
+    match &SETTINGS.auth_callback_url {
+        Some(url) => Box::new(HttpCallback::new(
+            url.clone(),
+            Duration::from_secs(SETTINGS.auth_callback_timeout),
+            Duration::from_secs(SETTINGS.auth_callback_cache_ttl),
+            SETTINGS.auth_callback_cache_size,
+        )),
+        None => Box::new(AuthJson),
+    }
+}
@@ -0,0 +1,357 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use super::{
+    acl::{Ruleset, Subject},
+    types::{AuthData, PackageName, Permissions},
+};
+use std::fmt::{self, Display};
+
+/// Whose rule or pattern a [`Problem`] refers to.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Owner {
+    Everyone,
+    User(String),
+    Group(String),
+    Network(String),
+    Token(String, String),
+    Licence(String, String),
+    Visibility,
+}
+
+impl Display for Owner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Owner::Everyone => write!(f, "Global packages"),
+            Owner::User(user) => write!(f, "User “{user}”"),
+            Owner::Group(group) => write!(f, "Group “{group}”"),
+            Owner::Network(network) => write!(f, "Network “{network}”"),
+            Owner::Token(user, token) => write!(f, "API token “{token}” of user “{user}”"),
+            Owner::Licence(user, licence) => write!(f, "Licence “{licence}” of user “{user}”"),
+            Owner::Visibility => write!(f, "Visibility"),
+        }
+    }
+}
+
+/// A mistake in the auth data that does not prevent it from being loaded,
+/// but most likely does not do what was intended.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Problem {
+    /// The user is a member of a group that is not defined.
+    UndefinedGroup { user: String, group: String },
+    /// The user’s password hash is of an unknown format and never matches.
+    UnknownHash { user: String },
+    /// The serial key hash of the user’s licence is of an unknown format and never matches.
+    UnknownSerialKeyHash { user: String, licence: String },
+    /// The package name pattern does not match any hosted package.
+    UnmatchedPattern { owner: Owner, pattern: String },
+    /// The ruleset does not match any hosted version of the matching packages.
+    UnmatchedRule { owner: Owner, pattern: String },
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::UndefinedGroup { user, group } => {
+                write!(
+                    f,
+                    "User “{user}” is a member of the undefined group “{group}”."
+                )
+            }
+            Problem::UnknownHash { user } => {
+                write!(f, "User “{user}” has a password hash of an unknown format.")
+            }
+            Problem::UnknownSerialKeyHash { user, licence } => {
+                write!(
+                    f,
+                    "Licence “{licence}” of user “{user}” has a serial key hash of an unknown format."
+                )
+            }
+            Problem::UnmatchedPattern { owner, pattern } => {
+                write!(f, "{owner}: “{pattern}” does not match any package.")
+            }
+            Problem::UnmatchedRule { owner, pattern } => {
+                write!(
+                    f,
+                    "{owner}: The rule of “{pattern}” does not match any version."
+                )
+            }
+        }
+    }
+}
+
+/// The hosted versions of a single package.
+pub type HostedPackage<'a> = (&'a str, Vec<Subject<'a>>);
+
+fn check_pattern(
+    problems: &mut Vec<Problem>,
+    owner: impl Fn() -> Owner,
+    label: &str,
+    pattern: &PackageName,
+    ruleset: Option<&Ruleset>,
+    packages: &[HostedPackage],
+) {
+    let mut versions = packages
+        .iter()
+        .filter(|(package_id, _)| pattern.0.is_match(package_id))
+        .flat_map(|(_, versions)| versions)
+        .peekable();
+
+    if versions.peek().is_none() {
+        problems.push(Problem::UnmatchedPattern {
+            owner: owner(),
+            pattern: label.to_owned(),
+        });
+    } else if let Some(ruleset) = ruleset {
+        if !versions.any(|subject| ruleset.evaluate(*subject)) {
+            problems.push(Problem::UnmatchedRule {
+                owner: owner(),
+                pattern: label.to_owned(),
+            });
+        }
+    }
+}
+
+fn check_permissions(
+    problems: &mut Vec<Problem>,
+    owner: impl Fn() -> Owner,
+    permissions: &Permissions,
+    packages: &[HostedPackage],
+) {
+    for (prefix, rules) in [("", &permissions.allow), ("!", &permissions.deny)] {
+        let mut rules = rules.iter().collect::<Vec<_>>();
+        rules.sort_by_key(|(pattern, _)| pattern.0.as_str());
+
+        for (pattern, ruleset) in rules {
+            let label = format!("{prefix}{pattern}");
+            check_pattern(problems, &owner, &label, pattern, Some(ruleset), packages);
+        }
+    }
+}
+
+/// Returns the problems of the auth data with regard to the hosted packages:
+/// Undefined groups, unknown password hashes, package name patterns that do
+/// not match any package and rulesets that do not match any version.
+pub fn check_auth_data(auth_data: &AuthData, packages: &[HostedPackage]) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    check_permissions(
+        &mut problems,
+        || Owner::Everyone,
+        &auth_data.packages,
+        packages,
+    );
+
+    let mut visibility = auth_data.visibility.iter().collect::<Vec<_>>();
+    visibility.sort_by_key(|(pattern, _)| pattern.0.as_str());
+
+    for (pattern, _) in visibility {
+        let label = pattern.to_string();
+        check_pattern(
+            &mut problems,
+            || Owner::Visibility,
+            &label,
+            pattern,
+            None,
+            packages,
+        );
+    }
+
+    let mut groups = auth_data.groups.iter().collect::<Vec<_>>();
+    groups.sort_by_key(|(name, _)| *name);
+
+    for (name, group) in groups {
+        let owner = || Owner::Group(name.clone());
+        check_permissions(&mut problems, owner, &group.packages, packages);
+    }
+
+    let mut networks = auth_data.networks.iter().collect::<Vec<_>>();
+    networks.sort_by_key(|(name, _)| *name);
+
+    for (name, network) in networks {
+        let owner = || Owner::Network(name.clone());
+        check_permissions(&mut problems, owner, &network.packages, packages);
+    }
+
+    let mut users = auth_data.users.iter().collect::<Vec<_>>();
+    users.sort_by_key(|(name, _)| *name);
+
+    for (name, user) in users {
+        if user.passwd.unknown_hash().is_some() {
+            problems.push(Problem::UnknownHash { user: name.clone() });
+        }
+
+        for membership in &user.groups {
+            if !auth_data.groups.contains_key(&membership.name) {
+                problems.push(Problem::UndefinedGroup {
+                    user: name.clone(),
+                    group: membership.name.clone(),
+                });
+            }
+        }
+
+        let owner = || Owner::User(name.clone());
+        check_permissions(&mut problems, owner, &user.packages, packages);
+
+        let mut tokens = user.tokens.iter().collect::<Vec<_>>();
+        tokens.sort_by_key(|(token, _)| *token);
+
+        for (token, scope) in tokens {
+            let owner = || Owner::Token(name.clone(), token.clone());
+
+            for pattern in scope.packages.iter().flatten() {
+                let label = pattern.to_string();
+                check_pattern(&mut problems, owner, &label, pattern, None, packages);
+            }
+        }
+
+        let mut licences = user.licences.iter().collect::<Vec<_>>();
+        licences.sort_by_key(|(number, _)| *number);
+
+        for (number, licence) in licences {
+            if licence.serial_key.unknown_hash().is_some() {
+                problems.push(Problem::UnknownSerialKeyHash {
+                    user: name.clone(),
+                    licence: number.clone(),
+                });
+            }
+
+            let owner = || Owner::Licence(name.clone(), number.clone());
+            check_permissions(&mut problems, owner, &licence.packages, packages);
+        }
+    }
+
+    problems
+}
+
+#[test]
+fn test_check_auth_data() {
+    use crate::{date::Date, version::Version};
+
+    let v1 = Version::parser("1.0.0").unwrap().1;
+    let v2 = Version::parser("2.0.0 Beta 1").unwrap().1;
+    let packages = [
+        (
+            "be.bastelstu.foo",
+            vec![
+                Subject {
+                    version: &v1,
+                    released: Some(Date::new(2025, 1, 1)),
+                },
+                Subject {
+                    version: &v2,
+                    released: Some(Date::new(2025, 6, 1)),
+                },
+            ],
+        ),
+        (
+            "be.bastelstu.bar",
+            vec![Subject {
+                version: &v1,
+                released: None,
+            }],
+        ),
+    ];
+
+    let auth_data = AuthData::try_from(
+        r#"{
+        "users": {
+            "Foo": {
+                "passwd": "Bcrypt:$2y$10$invalid",
+                "groups": ["Premium", "Missing"],
+                "packages": {
+                    "be.bastelstu.foo": "$v < 1.0.0",
+                    "!be.bastelstu.bar": "$v ~ beta"
+                },
+                "tokens": {
+                    "ci": {
+                        "sha256": "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+                        "packages": ["be.bastelstu.*", "com.example.*"]
+                    }
+                },
+                "licences": {
+                    "123456": {
+                        "serial_key": "md5:0cc175b9c0f1b6a831c399e269772661",
+                        "packages": {
+                            "be.bastelstu.qux": "*"
+                        }
+                    }
+                }
+            },
+            "Bar": {
+                "passwd": "md5:0cc175b9c0f1b6a831c399e269772661"
+            }
+        },
+        "groups": {
+            "Premium": {
+                "be.bastelstu.*": "$released >= 2025-06-01",
+                "be.bastelstu.baz": "*"
+            }
+        },
+        "packages": {
+            "be.bastelstu.bar": "*"
+        },
+        "visibility": {
+            "*": "accessible",
+            "com.example.*": "all"
+        }
+    }"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        check_auth_data(&auth_data, &packages),
+        vec![
+            Problem::UnmatchedPattern {
+                owner: Owner::Visibility,
+                pattern: "com.example.*".to_owned(),
+            },
+            Problem::UnmatchedPattern {
+                owner: Owner::Group("Premium".to_owned()),
+                pattern: "be.bastelstu.baz".to_owned(),
+            },
+            Problem::UnknownHash {
+                user: "Bar".to_owned()
+            },
+            Problem::UndefinedGroup {
+                user: "Foo".to_owned(),
+                group: "Missing".to_owned(),
+            },
+            Problem::UnmatchedRule {
+                owner: Owner::User("Foo".to_owned()),
+                pattern: "be.bastelstu.foo".to_owned(),
+            },
+            Problem::UnmatchedRule {
+                owner: Owner::User("Foo".to_owned()),
+                pattern: "!be.bastelstu.bar".to_owned(),
+            },
+            Problem::UnmatchedPattern {
+                owner: Owner::Token("Foo".to_owned(), "ci".to_owned()),
+                pattern: "com.example.*".to_owned(),
+            },
+            Problem::UnknownSerialKeyHash {
+                user: "Foo".to_owned(),
+                licence: "123456".to_owned(),
+            },
+            Problem::UnmatchedPattern {
+                owner: Owner::Licence("Foo".to_owned(), "123456".to_owned()),
+                pattern: "be.bastelstu.qux".to_owned(),
+            },
+        ]
+    );
+}
@@ -0,0 +1,200 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+type CacheKey = [u8; 32];
+
+/// Remembers successfully verified credentials, so that the (intentionally slow)
+/// password hash does not need to be computed on every request.
+///
+/// Credentials are never stored in plain text: Entries are keyed by an HMAC of
+/// the username and password, using a random key that never leaves the process.
+/// Next to the time of verification every entry holds a `T`, e.g. the user
+/// the credentials were resolved to.
+///
+/// The cache of an `AuthData` is owned by it, thus it is implicitly cleared
+/// whenever `AUTH_DATA` is swapped. Caches that outlive the auth data, like
+/// the one of the `HttpCallback`, must not return the entries of previously
+/// loaded auth data, see `AuthData::generation`.
+pub struct CredentialCache<T = ()> {
+    key: CacheKey,
+    entries: Mutex<HashMap<CacheKey, (Instant, T)>>,
+}
+
+impl<T: Clone> CredentialCache<T> {
+    pub fn new() -> Self {
+        let mut key = CacheKey::default();
+        getrandom::getrandom(&mut key).expect("the system’s random number generator to work");
+
+        Self {
+            key,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn cache_key(&self, username: &str, password: &str) -> CacheKey {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC to accept any key");
+
+        // Prefix the username with its length to keep `("ab", "c")`
+        // and `("a", "bc")` apart.
+        mac.update(&(username.len() as u64).to_le_bytes());
+        mac.update(username.as_bytes());
+        mac.update(password.as_bytes());
+
+        mac.finalize().into_bytes().into()
+    }
+
+    /// Returns the value of the credentials, if they have been
+    /// successfully verified within the last `ttl`.
+    pub fn get(&self, username: &str, password: &str, ttl: Duration) -> Option<T> {
+        let key = self.cache_key(username, password);
+        let entries = self.entries.lock().unwrap();
+
+        entries
+            .get(&key)
+            .filter(|(verified, _)| verified.elapsed() < ttl)
+            .map(|(_, value)| value.clone())
+    }
+
+    /// Remembers successfully verified credentials.
+    ///
+    /// At most `capacity` credentials are remembered, expired entries and
+    /// then the oldest entry are evicted if the cache is full.
+    pub fn insert(&self, username: &str, password: &str, value: T, ttl: Duration, capacity: usize) {
+        if capacity == 0 || ttl.is_zero() {
+            return;
+        }
+
+        let key = self.cache_key(username, password);
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+
+        if entries.len() >= capacity && !entries.contains_key(&key) {
+            entries.retain(|_, (verified, _)| now.duration_since(*verified) < ttl);
+
+            if entries.len() >= capacity {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, (verified, _))| *verified)
+                    .map(|(key, _)| *key);
+
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+
+        entries.insert(key, (now, value));
+    }
+}
+
+impl CredentialCache {
+    /// Returns whether the credentials are valid, calling `verify` only if they
+    /// have not been successfully verified within the last `ttl`.
+    ///
+    /// At most `capacity` credentials are remembered, a `capacity` of zero
+    /// disables the cache.
+    pub fn verify(
+        &self,
+        username: &str,
+        password: &str,
+        ttl: Duration,
+        capacity: usize,
+        verify: impl FnOnce() -> bool,
+    ) -> bool {
+        if capacity == 0 || ttl.is_zero() {
+            return verify();
+        }
+
+        if self.get(username, password, ttl).is_some() {
+            return true;
+        }
+
+        // Do not hold the lock while verifying, hashing is slow on purpose.
+        if !verify() {
+            return false;
+        }
+
+        self.insert(username, password, (), ttl, capacity);
+
+        true
+    }
+}
+
+impl<T: Clone> Default for CredentialCache<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> std::fmt::Debug for CredentialCache<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CredentialCache").finish_non_exhaustive()
+    }
+}
+
+#[test]
+fn test_verify() {
+    use std::cell::Cell;
+
+    let cache = CredentialCache::new();
+    let ttl = Duration::from_secs(60);
+    let calls = Cell::new(0);
+    let verify = |result| {
+        calls.set(calls.get() + 1);
+        result
+    };
+
+    // Failed verifications are not cached
+    assert!(!cache.verify("user", "wrong", ttl, 2, || verify(false)));
+    assert!(!cache.verify("user", "wrong", ttl, 2, || verify(false)));
+    assert_eq!(calls.get(), 2);
+
+    // Successful verifications are
+    assert!(cache.verify("user", "test", ttl, 2, || verify(true)));
+    assert!(cache.verify("user", "test", ttl, 2, || verify(true)));
+    assert_eq!(calls.get(), 3);
+
+    // The key covers both username and password
+    assert!(!cache.verify("use", "rtest", ttl, 2, || verify(false)));
+    assert!(!cache.verify("user", "test2", ttl, 2, || verify(false)));
+    assert_eq!(calls.get(), 5);
+
+    // The oldest entry is evicted once the cache is full
+    assert!(cache.verify("other", "test", ttl, 2, || verify(true)));
+    assert!(cache.verify("third", "test", ttl, 2, || verify(true)));
+    assert_eq!(calls.get(), 7);
+    assert!(cache.verify("third", "test", ttl, 2, || verify(true)));
+    assert_eq!(calls.get(), 7);
+    assert!(cache.verify("user", "test", ttl, 2, || verify(true)));
+    assert_eq!(calls.get(), 8);
+
+    // Expired entries are verified again
+    assert!(cache.verify("user", "test", Duration::ZERO, 2, || verify(true)));
+    assert_eq!(calls.get(), 9);
+    std::thread::sleep(Duration::from_millis(10));
+    assert!(cache.verify("user", "test", Duration::from_millis(5), 2, || verify(true)));
+    assert_eq!(calls.get(), 10);
+}
@@ -0,0 +1,177 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::version::Version;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::{
+    fmt::Write,
+    net::IpAddr,
+    time::{Duration, SystemTime},
+};
+
+/// A download link that grants access to a single version of a package
+/// without credentials until it expires, optionally only to a single IP address.
+///
+/// The link is authenticated by an HMAC-SHA256 of the package, the version,
+/// the expiry and the IP address, keyed with the `download_link_secret`.
+#[derive(Debug)]
+pub struct DownloadLink<'a> {
+    pub package_id: &'a str,
+    pub version: &'a Version,
+
+    /// The UNIX timestamp after which the link is rejected.
+    pub expires: u64,
+    pub ip: Option<IpAddr>,
+}
+
+impl DownloadLink<'_> {
+    fn mac(&self, secret: &str) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC to accept any key");
+
+        let ip = self.ip.map(|ip| ip.to_string()).unwrap_or_default();
+        mac.update(
+            format!(
+                "{}\n{}\n{}\n{}",
+                self.package_id, self.version, self.expires, ip
+            )
+            .as_bytes(),
+        );
+
+        mac
+    }
+
+    /// Returns the hexadecimal signature of the link.
+    pub fn signature(&self, secret: &str) -> String {
+        self.mac(secret)
+            .finalize()
+            .into_bytes()
+            .iter()
+            .fold(String::new(), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            })
+    }
+
+    /// Returns the link, relative to the package server.
+    pub fn path(&self, secret: &str) -> String {
+        let mut path = format!(
+            "/{}/{}/?expires={}",
+            self.package_id,
+            self.version.format_url(),
+            self.expires
+        );
+
+        if let Some(ip) = self.ip {
+            let _ = write!(path, "&ip={ip}");
+        }
+
+        let _ = write!(path, "&signature={}", self.signature(secret));
+
+        path
+    }
+
+    /// Returns whether `signature` is the link’s signature, the link has not
+    /// expired at `now` and, if the link is bound to an IP address, `client`
+    /// is this address.
+    pub fn is_valid(
+        &self,
+        secret: &str,
+        signature: &str,
+        client: Option<IpAddr>,
+        now: SystemTime,
+    ) -> bool {
+        let expires = SystemTime::UNIX_EPOCH + Duration::from_secs(self.expires);
+
+        if now > expires || self.ip.is_some_and(|ip| client != Some(ip.to_canonical())) {
+            return false;
+        }
+
+        let Some(signature) = super::hex::decode(signature) else {
+            return false;
+        };
+
+        self.mac(secret).verify_slice(&signature).is_ok()
+    }
+}
+
+#[test]
+fn test_is_valid() {
+    let version = Version::try_from("1.0.0 Beta 1").unwrap();
+    let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+    let ip = "192.0.2.1".parse::<IpAddr>().unwrap();
+
+    let link = DownloadLink {
+        package_id: "be.bastelstu.example",
+        version: &version,
+        expires: 1_000,
+        ip: None,
+    };
+    let signature = link.signature("secret");
+
+    assert!(link
+        .path("secret")
+        .starts_with("/be.bastelstu.example/1.0.0_beta_1/?expires=1000&signature="));
+    assert!(link.is_valid("secret", &signature, None, at(1_000)));
+    assert!(link.is_valid("secret", &signature, Some(ip), at(500)));
+    assert!(!link.is_valid("secret", &signature, None, at(1_001)));
+    assert!(!link.is_valid("other", &signature, None, at(500)));
+    assert!(!link.is_valid("secret", &signature[2..], None, at(500)));
+    assert!(!link.is_valid("secret", "zz", None, at(500)));
+
+    // The signature covers the package, the version and the expiry
+    let other_version = Version::try_from("1.0.0").unwrap();
+    for other in [
+        DownloadLink {
+            package_id: "be.bastelstu.other",
+            ..link
+        },
+        DownloadLink {
+            version: &other_version,
+            ..link
+        },
+        DownloadLink {
+            expires: 2_000,
+            ..link
+        },
+        DownloadLink {
+            ip: Some(ip),
+            ..link
+        },
+    ] {
+        assert!(!other.is_valid("secret", &signature, Some(ip), at(500)));
+    }
+
+    // Links bound to an IP address are only valid for this address
+    let link = DownloadLink {
+        ip: Some(ip),
+        ..link
+    };
+    let signature = link.signature("secret");
+
+    assert!(link.path("secret").contains("&ip=192.0.2.1&"));
+    assert!(link.is_valid("secret", &signature, Some(ip), at(500)));
+    assert!(!link.is_valid("secret", &signature, None, at(500)));
+    assert!(!link.is_valid(
+        "secret",
+        &signature,
+        Some("192.0.2.2".parse().unwrap()),
+        at(500)
+    ));
+}
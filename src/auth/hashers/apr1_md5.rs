@@ -0,0 +1,141 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::auth::PasswordHash;
+use md5::{Digest, Md5};
+use subtle::ConstantTimeEq;
+
+const MAGIC: &str = "$apr1$";
+const ITOA64: &[u8; 64] = b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// The Apache variant of the MD5-based crypt, as used by `htpasswd -m`.
+#[derive(Debug)]
+pub struct Apr1Md5(pub String);
+
+impl PasswordHash for Apr1Md5 {
+    fn verify(&self, password: &str) -> bool {
+        match verify(password, &self.0) {
+            Ok(result) => result,
+            Err(err) => {
+                log::error!("Failed to verify password: {}", err);
+                false
+            }
+        }
+    }
+}
+
+fn verify(password: &str, hash: &str) -> crate::Result<bool> {
+    let salt = hash
+        .strip_prefix(MAGIC)
+        .and_then(|rest| rest.split('$').next())
+        .ok_or("Failed to parse password hash")?;
+
+    Ok(apr1_md5(password.as_bytes(), salt.as_bytes())
+        .as_bytes()
+        .ct_eq(hash.as_bytes())
+        .into())
+}
+
+/// See <https://svn.apache.org/viewvc/apr/apr/trunk/crypto/apr_md5.c>
+fn apr1_md5(password: &[u8], salt: &[u8]) -> String {
+    let salt = &salt[..salt.len().min(8)];
+
+    let alternate = Md5::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(password)
+        .finalize();
+
+    let mut context = Md5::new()
+        .chain_update(password)
+        .chain_update(MAGIC)
+        .chain_update(salt);
+
+    for chunk in password.chunks(16) {
+        context.update(&alternate[..chunk.len()]);
+    }
+
+    let mut length = password.len();
+    while length > 0 {
+        if length & 1 == 1 {
+            context.update([0]);
+        } else {
+            context.update(&password[..1]);
+        }
+
+        length >>= 1;
+    }
+
+    let mut digest = context.finalize();
+
+    for round in 0..1000 {
+        let mut context = Md5::new();
+
+        if round & 1 == 1 {
+            context.update(password);
+        } else {
+            context.update(digest);
+        }
+
+        if round % 3 != 0 {
+            context.update(salt);
+        }
+
+        if round % 7 != 0 {
+            context.update(password);
+        }
+
+        if round & 1 == 1 {
+            context.update(digest);
+        } else {
+            context.update(password);
+        }
+
+        digest = context.finalize();
+    }
+
+    let mut result = format!("{}{}$", MAGIC, String::from_utf8_lossy(salt));
+
+    let mut encode = |value: u32, length: usize| {
+        let mut value = value;
+
+        for _ in 0..length {
+            result.push(ITOA64[(value & 0x3f) as usize] as char);
+            value >>= 6;
+        }
+    };
+
+    for (a, b, c) in [(0, 6, 12), (1, 7, 13), (2, 8, 14), (3, 9, 15), (4, 10, 5)] {
+        encode(
+            (u32::from(digest[a]) << 16) | (u32::from(digest[b]) << 8) | u32::from(digest[c]),
+            4,
+        );
+    }
+    encode(u32::from(digest[11]), 2);
+
+    result
+}
+
+#[test]
+fn test_verify_method() {
+    assert!(Apr1Md5("$apr1$saltsalt$NBMPZFGR7yazCdGvlWJji1".to_owned()).verify("test"));
+    assert!(!Apr1Md5("$apr1$saltsalt$NBMPZFGR7yazCdGvlWJji1".to_owned()).verify("root"));
+
+    // The regular MD5-based crypt is not accepted
+    assert!(!Apr1Md5("$1$abc$B..HicC/afMveWeNyfNsf/".to_owned()).verify("test"));
+}
@@ -0,0 +1,70 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use super::phc;
+use crate::auth::PasswordHash;
+use argon2::{Algorithm, Argon2};
+
+#[derive(Debug)]
+pub struct Argon2id(pub String);
+
+impl Argon2id {
+    /// Hashes `password` using the default parameters.
+    pub fn hash(password: &str) -> crate::Result<Self> {
+        phc::hash(password, &Argon2::default()).map(Self)
+    }
+}
+
+impl PasswordHash for Argon2id {
+    fn verify(&self, password: &str) -> bool {
+        let algorithms = [Algorithm::Argon2id.ident().as_str()];
+
+        match phc::verify(password, &self.0, &algorithms, &Argon2::default()) {
+            Ok(result) => result,
+            Err(err) => {
+                log::error!("Failed to verify password: {}", err);
+                false
+            }
+        }
+    }
+}
+
+#[test]
+fn test_verify_method() {
+    assert!(Argon2id(
+        "$argon2id$v=19$m=256,t=1,p=1$dGltcy1wYWNrYWdlLXNlcnZlcg$U0yEKLKYEavOUFTuXHDpweUbDNfRP642D0UPzguL7ug".to_owned()
+    )
+    .verify("test"));
+
+    assert!(!Argon2id(
+        "$argon2id$v=19$m=256,t=1,p=1$dGltcy1wYWNrYWdlLXNlcnZlcg$U0yEKLKYEavOUFTuXHDpweUbDNfRP642D0UPzguL7ug".to_owned()
+    )
+    .verify("root"));
+
+    assert!(
+        !Argon2id("$2a$08$3GNrFLqG5M7BsGI/BtxcGuNWX2iY/UsfTwWnmJiddHB.z/PdkAsR2".to_owned())
+            .verify("root")
+    );
+
+    // Argon2i and Argon2d hashes are not accepted
+    assert!(!Argon2id(
+        "$argon2i$v=19$m=256,t=1,p=1$dGltcy1wYWNrYWdlLXNlcnZlcg$ioqsKz5ohTFvfo0Qb2gFhFOyb6B2zLdayX01cBZYyB8"
+            .to_owned()
+    )
+    .verify("test"));
+}
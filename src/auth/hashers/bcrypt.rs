@@ -21,6 +21,13 @@ use crate::auth::PasswordHash;
 #[derive(Debug)]
 pub struct Bcrypt(pub String);
 
+impl Bcrypt {
+    /// Hashes `password` using the given cost, i.e. 2^`cost` rounds.
+    pub fn hash(password: &str, cost: u32) -> crate::Result<Self> {
+        Ok(Self(bcrypt::hash(password, cost)?))
+    }
+}
+
 impl PasswordHash for Bcrypt {
     fn verify(&self, password: &str) -> bool {
         match bcrypt::verify(password, &self.0) {
@@ -18,15 +18,36 @@
 
 use crate::auth::PasswordHash;
 
+mod phc;
+
+mod apr1_md5;
+pub use self::apr1_md5::*;
+
+mod argon2;
+pub use self::argon2::*;
+
 mod bcrypt;
 pub use self::bcrypt::*;
 
 mod double_bcrypt;
 pub use self::double_bcrypt::*;
 
+mod pbkdf2;
+pub use self::pbkdf2::*;
+
+mod scrypt;
+pub use self::scrypt::*;
+
+mod sha1;
+pub use self::sha1::*;
+
 #[derive(Debug)]
 pub struct UnknownHash(pub String);
-impl PasswordHash for UnknownHash {}
+impl PasswordHash for UnknownHash {
+    fn unknown_hash(&self) -> Option<&str> {
+        Some(&self.0)
+    }
+}
 
 #[derive(Debug)]
 pub struct BannedUser;
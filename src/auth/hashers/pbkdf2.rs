@@ -0,0 +1,63 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use super::phc;
+use crate::auth::PasswordHash;
+use pbkdf2::{Algorithm, Pbkdf2};
+
+#[derive(Debug)]
+pub struct Pbkdf2Sha256(pub String);
+
+impl Pbkdf2Sha256 {
+    /// Hashes `password` using the recommended number of rounds.
+    pub fn hash(password: &str) -> crate::Result<Self> {
+        phc::hash(password, &Pbkdf2).map(Self)
+    }
+}
+
+impl PasswordHash for Pbkdf2Sha256 {
+    fn verify(&self, password: &str) -> bool {
+        let algorithms = [Algorithm::Pbkdf2Sha256.ident().as_str()];
+
+        match phc::verify(password, &self.0, &algorithms, &Pbkdf2) {
+            Ok(result) => result,
+            Err(err) => {
+                log::error!("Failed to verify password: {}", err);
+                false
+            }
+        }
+    }
+}
+
+#[test]
+fn test_verify_method() {
+    assert!(Pbkdf2Sha256(
+        "$pbkdf2-sha256$i=1000,l=32$dGltcy1wYWNrYWdlLXNlcnZlcg$fvE7+FtZBB1HD9qo8va1J05uy0j6Xt4VSfjTNCRg9cg".to_owned()
+    )
+    .verify("test"));
+
+    assert!(!Pbkdf2Sha256(
+        "$pbkdf2-sha256$i=1000,l=32$dGltcy1wYWNrYWdlLXNlcnZlcg$fvE7+FtZBB1HD9qo8va1J05uy0j6Xt4VSfjTNCRg9cg".to_owned()
+    )
+    .verify("root"));
+
+    assert!(!Pbkdf2Sha256(
+        "$2a$08$3GNrFLqG5M7BsGI/BtxcGuNWX2iY/UsfTwWnmJiddHB.z/PdkAsR2".to_owned()
+    )
+    .verify("root"));
+}
@@ -0,0 +1,55 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+
+/// Verifies `password` against a hash in the PHC string format,
+/// provided the hash uses one of the given `algorithms`.
+///
+/// See <https://github.com/P-H-C/phc-string-format/blob/master/phc-sf-spec.md>
+pub fn verify(
+    password: &str,
+    hash: &str,
+    algorithms: &[&str],
+    verifier: &dyn PasswordVerifier,
+) -> crate::Result<bool> {
+    let hash = PasswordHash::new(hash).map_err(|err| err.to_string())?;
+
+    if !algorithms.contains(&hash.algorithm.as_str()) {
+        return Err(format!("Unexpected password hash algorithm: {}", hash.algorithm).into());
+    }
+
+    match verifier.verify_password(password.as_bytes(), &hash) {
+        Ok(()) => Ok(true),
+        Err(password_hash::Error::Password) => Ok(false),
+        Err(err) => Err(err.to_string().into()),
+    }
+}
+
+/// Hashes `password` with a random salt, returning the hash in the PHC string format.
+pub fn hash(password: &str, hasher: &impl PasswordHasher) -> crate::Result<String> {
+    let mut salt = [0; 16];
+    getrandom::getrandom(&mut salt)?;
+
+    let salt = SaltString::encode_b64(&salt).map_err(|err| err.to_string())?;
+
+    hasher
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|err| err.to_string().into())
+}
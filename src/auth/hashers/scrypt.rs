@@ -0,0 +1,61 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use super::phc;
+use crate::auth::PasswordHash;
+use scrypt::{Scrypt as Algorithm, ALG_ID};
+
+#[derive(Debug)]
+pub struct Scrypt(pub String);
+
+impl Scrypt {
+    /// Hashes `password` using the recommended parameters.
+    pub fn hash(password: &str) -> crate::Result<Self> {
+        phc::hash(password, &Algorithm).map(Self)
+    }
+}
+
+impl PasswordHash for Scrypt {
+    fn verify(&self, password: &str) -> bool {
+        match phc::verify(password, &self.0, &[ALG_ID.as_str()], &Algorithm) {
+            Ok(result) => result,
+            Err(err) => {
+                log::error!("Failed to verify password: {}", err);
+                false
+            }
+        }
+    }
+}
+
+#[test]
+fn test_verify_method() {
+    assert!(Scrypt(
+        "$scrypt$ln=4,r=8,p=1$dGltcy1wYWNrYWdlLXNlcnZlcg$yRhZYWFqnqTqMtXSsyVH3vI9O7wn2V9qEtoPbnuOygI".to_owned()
+    )
+    .verify("test"));
+
+    assert!(!Scrypt(
+        "$scrypt$ln=4,r=8,p=1$dGltcy1wYWNrYWdlLXNlcnZlcg$yRhZYWFqnqTqMtXSsyVH3vI9O7wn2V9qEtoPbnuOygI".to_owned()
+    )
+    .verify("root"));
+
+    assert!(
+        !Scrypt("$2a$08$3GNrFLqG5M7BsGI/BtxcGuNWX2iY/UsfTwWnmJiddHB.z/PdkAsR2".to_owned())
+            .verify("root")
+    );
+}
@@ -0,0 +1,50 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::auth::PasswordHash;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use sha1::{Digest, Sha1 as Sha1Hasher};
+use subtle::ConstantTimeEq;
+
+/// An unsalted SHA-1 digest in Base64, as used by `htpasswd -s`.
+///
+/// Only meant for migrating existing htpasswd files, it is trivial to brute-force.
+#[derive(Debug)]
+pub struct Sha1(pub String);
+
+impl PasswordHash for Sha1 {
+    fn verify(&self, password: &str) -> bool {
+        match STANDARD.decode(&self.0) {
+            Ok(digest) => Sha1Hasher::digest(password.as_bytes())
+                .as_slice()
+                .ct_eq(&digest)
+                .into(),
+            Err(err) => {
+                log::error!("Failed to verify password: {}", err);
+                false
+            }
+        }
+    }
+}
+
+#[test]
+fn test_verify_method() {
+    assert!(Sha1("qUqP5cyxm6YcTAhz05Hph5gvu9M=".to_owned()).verify("test"));
+    assert!(!Sha1("qUqP5cyxm6YcTAhz05Hph5gvu9M=".to_owned()).verify("root"));
+    assert!(!Sha1("$apr1$saltsalt$NBMPZFGR7yazCdGvlWJji1".to_owned()).verify("test"));
+}
@@ -18,11 +18,23 @@
 
 pub mod acl;
 mod auth_json_reader;
+pub mod authenticator;
+mod check;
+mod credential_cache;
+mod download_link;
 mod hashers;
+mod hex;
+mod network;
 mod password;
+mod sqlite_reader;
+mod throttle;
 mod types;
 
 pub use auth_json_reader::*;
+pub use check::*;
+pub use download_link::*;
 
+pub use network::*;
 pub use password::*;
+pub use throttle::*;
 pub use types::*;
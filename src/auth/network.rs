@@ -0,0 +1,235 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use serde::{
+    de::{SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{
+    error,
+    fmt::{self, Display},
+    net::IpAddr,
+    str::FromStr,
+};
+
+#[derive(Debug)]
+pub struct InvalidNetwork(String);
+
+impl Display for InvalidNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "“{}” is not a valid IP address or network.", self.0)
+    }
+}
+
+impl error::Error for InvalidNetwork {}
+
+/// An IP network in CIDR notation, e.g. `192.0.2.0/24` or `2001:db8::/32`.
+/// A plain address is the network of only this address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Network {
+    address: IpAddr,
+    prefix: u8,
+}
+
+impl Network {
+    /// Returns whether `ip` is part of the network. IPv4-mapped IPv6
+    /// addresses are treated as the IPv4 addresses they map.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.address, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Network {
+    type Err = InvalidNetwork;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidNetwork(s.to_owned());
+
+        let (address, prefix) = match s.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (s, None),
+        };
+
+        let address = address
+            .parse::<IpAddr>()
+            .map_err(|_| invalid())?
+            .to_canonical();
+        let max = if address.is_ipv4() { 32 } else { 128 };
+
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|prefix| *prefix <= max)
+                .ok_or_else(invalid)?,
+            None => max,
+        };
+
+        Ok(Self { address, prefix })
+    }
+}
+
+impl Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix)
+    }
+}
+
+struct NetworkVisitor;
+
+impl<'de> Visitor<'de> for NetworkVisitor {
+    type Value = Network;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an IP address or network in CIDR notation")
+    }
+
+    fn visit_str<E>(self, s: &str) -> std::result::Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Network {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(NetworkVisitor)
+    }
+}
+
+impl Serialize for Network {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+struct NetworkListVisitor;
+
+impl<'de> Visitor<'de> for NetworkListVisitor {
+    type Value = Vec<Network>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of IP addresses or networks")
+    }
+
+    fn visit_str<E>(self, s: &str) -> std::result::Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        s.split(',')
+            .map(str::trim)
+            .filter(|network| !network.is_empty())
+            .map(|network| network.parse().map_err(serde::de::Error::custom))
+            .collect()
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut networks = Vec::new();
+
+        while let Some(network) = seq.next_element()? {
+            networks.push(network);
+        }
+
+        Ok(networks)
+    }
+}
+
+/// Deserializes a list of networks, given either as a sequence or as a
+/// comma separated string, e.g. within an environment variable.
+pub fn deserialize_network_list<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Network>, D::Error> {
+    deserializer.deserialize_any(NetworkListVisitor)
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(
+        "192.0.2.0/24".parse::<Network>().unwrap().to_string(),
+        "192.0.2.0/24"
+    );
+    assert_eq!(
+        "192.0.2.1".parse::<Network>().unwrap().to_string(),
+        "192.0.2.1/32"
+    );
+    assert_eq!(
+        "2001:db8::/32".parse::<Network>().unwrap().to_string(),
+        "2001:db8::/32"
+    );
+    assert_eq!(
+        "::ffff:192.0.2.1".parse::<Network>().unwrap().to_string(),
+        "192.0.2.1/32"
+    );
+
+    assert!("192.0.2.0/33".parse::<Network>().is_err());
+    assert!("192.0.2.0/".parse::<Network>().is_err());
+    assert!("example.com".parse::<Network>().is_err());
+}
+
+#[test]
+fn test_contains() {
+    let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+
+    let network = "192.0.2.0/24".parse::<Network>().unwrap();
+    assert!(network.contains(ip("192.0.2.0")));
+    assert!(network.contains(ip("192.0.2.255")));
+    assert!(network.contains(ip("::ffff:192.0.2.1")));
+    assert!(!network.contains(ip("192.0.3.0")));
+    assert!(!network.contains(ip("2001:db8::1")));
+
+    let network = "2001:db8::/32".parse::<Network>().unwrap();
+    assert!(network.contains(ip("2001:db8:ffff::1")));
+    assert!(!network.contains(ip("2001:db9::1")));
+
+    assert!("0.0.0.0/0"
+        .parse::<Network>()
+        .unwrap()
+        .contains(ip("203.0.113.1")));
+    assert!("::/0"
+        .parse::<Network>()
+        .unwrap()
+        .contains(ip("2001:db8::1")));
+    assert!("192.0.2.1"
+        .parse::<Network>()
+        .unwrap()
+        .contains(ip("192.0.2.1")));
+    assert!(!"192.0.2.1"
+        .parse::<Network>()
+        .unwrap()
+        .contains(ip("192.0.2.2")));
+}
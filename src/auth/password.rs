@@ -16,7 +16,9 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use super::hashers::{BannedUser, Bcrypt, DoubleBcrypt, UnknownHash};
+use super::hashers::{
+    Apr1Md5, Argon2id, BannedUser, Bcrypt, DoubleBcrypt, Pbkdf2Sha256, Scrypt, Sha1, UnknownHash,
+};
 use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::{de::Visitor, Deserialize, Deserializer};
@@ -26,6 +28,11 @@ pub trait PasswordHash: Debug + Send + Sync {
     fn verify(&self, _password: &str) -> bool {
         false
     }
+
+    /// Returns the hash if its format is not known, thus never matching any password.
+    fn unknown_hash(&self) -> Option<&str> {
+        None
+    }
 }
 
 struct PasswordHashVisitor;
@@ -55,10 +62,16 @@ impl<'de> Visitor<'de> for PasswordHashVisitor {
         if let (Some(name), Some(hash)) = (split.next(), split.next()) {
             assert_eq!(None, split.next());
 
-            let name = name.to_ascii_lowercase();
+            let hash = hash.to_owned();
 
-            if name == "bcrypt" {
-                return Ok(Box::new(Bcrypt(hash.to_owned())));
+            match name.to_ascii_lowercase().as_str() {
+                "bcrypt" => return Ok(Box::new(Bcrypt(hash))),
+                "argon2id" => return Ok(Box::new(Argon2id(hash))),
+                "scrypt" => return Ok(Box::new(Scrypt(hash))),
+                "pbkdf2-sha256" => return Ok(Box::new(Pbkdf2Sha256(hash))),
+                "apr1-md5" => return Ok(Box::new(Apr1Md5(hash))),
+                "sha1" => return Ok(Box::new(Sha1(hash))),
+                _ => (),
             }
         }
 
@@ -66,8 +79,76 @@ impl<'de> Visitor<'de> for PasswordHashVisitor {
     }
 }
 
+/// The algorithms [`hash_password`] supports, the first one is the default.
+pub const HASH_ALGORITHMS: [&str; 4] = ["bcrypt", "argon2id", "scrypt", "pbkdf2-sha256"];
+
+/// The bcrypt cost used if none is given.
+pub const DEFAULT_BCRYPT_COST: u32 = bcrypt::DEFAULT_COST;
+
+/// Hashes `password` with one of the [`HASH_ALGORITHMS`], returning the hash
+/// in the format expected by `auth.json`, e.g. `Bcrypt:$2b$12$…`.
+///
+/// A `cost` may only be given for bcrypt, the other algorithms use
+/// their recommended parameters.
+pub fn hash_password(algorithm: &str, password: &str, cost: Option<u32>) -> crate::Result<String> {
+    if cost.is_some() && algorithm != "bcrypt" {
+        return Err(format!("A cost can only be given for bcrypt, not for {algorithm}").into());
+    }
+
+    let hash = match algorithm {
+        "bcrypt" => format!(
+            "Bcrypt:{}",
+            Bcrypt::hash(password, cost.unwrap_or(DEFAULT_BCRYPT_COST))?.0
+        ),
+        "argon2id" => format!("Argon2id:{}", Argon2id::hash(password)?.0),
+        "scrypt" => format!("Scrypt:{}", Scrypt::hash(password)?.0),
+        "pbkdf2-sha256" => format!("PBKDF2-SHA256:{}", Pbkdf2Sha256::hash(password)?.0),
+        _ => {
+            return Err(format!(
+                "Unknown algorithm “{algorithm}”, expected one of: {}",
+                HASH_ALGORITHMS.join(", ")
+            )
+            .into())
+        }
+    };
+
+    Ok(hash)
+}
+
+/// Returns the password hash of an entry of an Apache htpasswd file.
+///
+/// Bcrypt, APR1-MD5 and SHA-1 hashes are supported, other hashes
+/// (i.e. `crypt(3)` and plain text) never match.
+pub fn from_htpasswd(hash: &str) -> Box<dyn PasswordHash> {
+    if hash.starts_with("$2") {
+        Box::new(Bcrypt(hash.to_owned()))
+    } else if hash.starts_with("$apr1$") {
+        Box::new(Apr1Md5(hash.to_owned()))
+    } else if let Some(digest) = hash.strip_prefix("{SHA}") {
+        Box::new(Sha1(digest.to_owned()))
+    } else {
+        Box::new(UnknownHash(hash.to_owned()))
+    }
+}
+
 impl<'de> Deserialize<'de> for Box<dyn PasswordHash> {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
         deserializer.deserialize_str(PasswordHashVisitor)
     }
 }
+
+#[test]
+fn test_hash_password() {
+    use serde::de::{value::StrDeserializer, IntoDeserializer};
+
+    let hash = hash_password("bcrypt", "test", Some(4)).unwrap();
+    assert!(hash.starts_with("Bcrypt:$2b$04$"));
+
+    let deserializer: StrDeserializer<serde::de::value::Error> = hash.as_str().into_deserializer();
+    let hash = Box::<dyn PasswordHash>::deserialize(deserializer).unwrap();
+    assert!(hash.verify("test"));
+    assert!(!hash.verify("root"));
+
+    assert!(hash_password("argon2id", "test", Some(4)).is_err());
+    assert!(hash_password("md5", "test", None).is_err());
+}
@@ -0,0 +1,232 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use super::{
+    acl::Ruleset,
+    auth_json_reader::AuthParseError,
+    password::PasswordHash,
+    types::{AuthData, GroupInfo, GroupMembership, Permissions, UserInfo},
+};
+use crate::date::Date;
+use rusqlite::{Connection, OpenFlags};
+use serde::{de::IntoDeserializer, Deserialize};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt::Display,
+    path::Path,
+    time::Duration,
+};
+
+type Result<T> = std::result::Result<T, AuthParseError>;
+
+impl From<rusqlite::Error> for AuthParseError {
+    fn from(err: rusqlite::Error) -> Self {
+        AuthParseError::UnableToReadDatabase(err)
+    }
+}
+
+fn invalid(what: impl Display) -> AuthParseError {
+    AuthParseError::InvalidDatabaseValue(what.to_string())
+}
+
+fn parse_date(table: &str, value: Option<String>) -> Result<Option<Date>> {
+    value
+        .map(|value| {
+            Date::try_from(value.as_str())
+                .map_err(|_| invalid(format!("{table}: invalid date “{value}”")))
+        })
+        .transpose()
+}
+
+fn parse_passwd(user: &str, value: &str) -> Result<Box<dyn PasswordHash>> {
+    let deserializer: serde::de::value::StrDeserializer<serde::de::value::Error> =
+        value.into_deserializer();
+
+    Box::<dyn PasswordHash>::deserialize(deserializer)
+        .map_err(|err| invalid(format!("users: invalid password of “{user}”: {err}")))
+}
+
+/// Reads auth data from an SQLite database, see `packages/auth.sql.example` for its schema.
+///
+/// The database is read within a single transaction, so that concurrent
+/// edits are either seen completely or not at all. Like a single fragment
+/// of `auth.d` the data is not validated.
+pub fn read_auth_database(path: &Path) -> Result<AuthData> {
+    let mut connection = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    connection.busy_timeout(Duration::from_secs(5))?;
+
+    let transaction = connection.transaction()?;
+    let mut auth_data = AuthData::default();
+
+    let mut statement = transaction.prepare("SELECT name, passwd, valid_until FROM users")?;
+    let mut rows = statement.query([])?;
+
+    while let Some(row) = rows.next()? {
+        let name: String = row.get(0)?;
+        let passwd: String = row.get(1)?;
+
+        let user = UserInfo {
+            passwd: parse_passwd(&name, &passwd)?,
+            valid_until: parse_date("users", row.get(2)?)?,
+            groups: Vec::new(),
+            packages: Permissions::default(),
+            tokens: HashMap::new(),
+            licences: HashMap::new(),
+            download_quota: None,
+        };
+
+        auth_data.users.insert(name, user);
+    }
+
+    let mut statement = transaction.prepare("SELECT name FROM groups")?;
+    let mut rows = statement.query([])?;
+
+    while let Some(row) = rows.next()? {
+        auth_data.groups.insert(row.get(0)?, GroupInfo::default());
+    }
+
+    let mut statement = transaction
+        .prepare("SELECT user, group_name, valid_until FROM memberships ORDER BY rowid")?;
+    let mut rows = statement.query([])?;
+
+    while let Some(row) = rows.next()? {
+        let user: String = row.get(0)?;
+        let membership = GroupMembership {
+            name: row.get(1)?,
+            valid_until: parse_date("memberships", row.get(2)?)?,
+        };
+
+        auth_data
+            .users
+            .get_mut(&user)
+            .ok_or_else(|| invalid(format!("memberships: unknown user “{user}”")))?
+            .groups
+            .push(membership);
+    }
+
+    let mut rules = HashMap::<(Option<String>, Option<String>), HashMap<String, Ruleset>>::new();
+    let mut statement =
+        transaction.prepare("SELECT user, group_name, package, ruleset FROM rules")?;
+    let mut rows = statement.query([])?;
+
+    while let Some(row) = rows.next()? {
+        let owner = (row.get(0)?, row.get(1)?);
+        let package: String = row.get(2)?;
+        let ruleset: String = row.get(3)?;
+
+        let ruleset = Ruleset::parser(&ruleset)
+            .map_err(|err| invalid(format!("rules: invalid ruleset “{ruleset}”: {err}")))?;
+
+        match rules.entry(owner).or_default().entry(package) {
+            Entry::Occupied(entry) => {
+                return Err(invalid(format!(
+                    "rules: package rule “{}” is defined more than once",
+                    entry.key()
+                )));
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(ruleset);
+            }
+        }
+    }
+
+    for ((user, group), rules) in rules {
+        let permissions = Permissions::try_from(rules)
+            .map_err(|err| invalid(format!("rules: invalid package: {err}")))?;
+
+        let target = match (user, group) {
+            (Some(user), None) => auth_data
+                .users
+                .get_mut(&user)
+                .map(|user| &mut user.packages)
+                .ok_or_else(|| invalid(format!("rules: unknown user “{user}”")))?,
+            (None, Some(group)) => auth_data
+                .groups
+                .get_mut(&group)
+                .map(|group| &mut group.packages)
+                .ok_or_else(|| invalid(format!("rules: unknown group “{group}”")))?,
+            (None, None) => &mut auth_data.packages,
+            (Some(_), Some(_)) => {
+                return Err(invalid(
+                    "rules: a rule must not belong to both a user and a group",
+                ));
+            }
+        };
+
+        *target = permissions;
+    }
+
+    Ok(auth_data)
+}
+
+#[test]
+fn test_read_auth_database() {
+    let path = std::env::temp_dir().join(format!("auth-sqlite-test-{}", std::process::id()));
+    let connection = Connection::open(&path).unwrap();
+    connection
+        .execute_batch(include_str!("../../packages/auth.sql.example"))
+        .unwrap();
+    connection
+        .execute_batch(
+            r#"
+            INSERT INTO users (name, passwd, valid_until) VALUES
+                ('Customer', 'apr1-md5:$apr1$saltsalt$NBMPZFGR7yazCdGvlWJji1', '2025-06-01'),
+                ('Banned', '-', NULL);
+            INSERT INTO groups (name) VALUES ('Premium');
+            INSERT INTO memberships (user, group_name, valid_until) VALUES
+                ('Customer', 'Premium', '2025-03-01'),
+                ('Customer', 'Basic', NULL);
+            INSERT INTO rules (user, group_name, package, ruleset) VALUES
+                ('Customer', NULL, 'be.bastelstu.user', '$v < 2.0.0'),
+                ('Customer', NULL, '!be.bastelstu.user', '$v ~ dev'),
+                (NULL, 'Premium', 'be.bastelstu.premium.*', '*'),
+                (NULL, NULL, 'be.bastelstu.free', '*');
+            "#,
+        )
+        .unwrap();
+
+    let data = read_auth_database(&path).unwrap();
+    assert_eq!(data.users.len(), 2);
+    let customer = &data.users["Customer"];
+    assert!(customer.passwd.verify("test"));
+    assert_eq!(customer.valid_until, Some(Date::new(2025, 6, 1)));
+    assert_eq!(customer.groups.len(), 2);
+    assert_eq!(customer.groups[0].name, "Premium");
+    assert_eq!(customer.groups[0].valid_until, Some(Date::new(2025, 3, 1)));
+    assert_eq!(customer.packages.allow.len(), 1);
+    assert_eq!(customer.packages.deny.len(), 1);
+    assert!(!data.users["Banned"].passwd.verify("-"));
+    assert_eq!(data.groups["Premium"].packages.allow.len(), 1);
+    assert_eq!(data.packages.allow.len(), 1);
+
+    connection
+        .execute(
+            "INSERT INTO rules (package, ruleset) VALUES ('be.bastelstu.broken', '$v ~')",
+            [],
+        )
+        .unwrap();
+    assert!(matches!(
+        read_auth_database(&path),
+        Err(AuthParseError::InvalidDatabaseValue(..))
+    ));
+
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,259 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// The maximum number of keys to track, the least recently failed
+/// keys are forgotten first.
+const CAPACITY: usize = 65_536;
+
+/// When and for how long failed attempts lock out further attempts.
+#[derive(Debug, Clone, Copy)]
+pub struct Backoff {
+    /// The number of failures that trigger the first lockout, zero disables the lockout.
+    pub threshold: u32,
+
+    /// The duration of the first lockout, every further failure doubles it.
+    pub base: Duration,
+
+    /// The maximum duration of a lockout. Failures are forgotten once
+    /// no further failure happened for this long after the lockout ended.
+    pub max: Duration,
+}
+
+impl Backoff {
+    fn lockout(&self, failures: u32) -> Duration {
+        if self.threshold == 0 || failures < self.threshold {
+            return Duration::ZERO;
+        }
+
+        let exponent = (failures - self.threshold).min(31);
+
+        self.base
+            .checked_mul(1 << exponent)
+            .unwrap_or(self.max)
+            .min(self.max)
+    }
+}
+
+#[derive(Debug)]
+struct Failures {
+    count: u32,
+    last: Instant,
+
+    /// The position of the key in [`Tracked::order`].
+    sequence: u64,
+}
+
+#[derive(Debug, Default)]
+struct Tracked {
+    failures: HashMap<String, Failures>,
+
+    /// The tracked keys, from the least to the most recently failed.
+    order: BTreeMap<u64, String>,
+    next_sequence: u64,
+}
+
+impl Tracked {
+    fn remove(&mut self, key: &str) {
+        if let Some(failures) = self.failures.remove(key) {
+            self.order.remove(&failures.sequence);
+        }
+    }
+}
+
+/// Tracks failed attempts per key (e.g. per IP address or per username)
+/// and locks out keys with too many failures using an exponential backoff.
+///
+/// At most `capacity` keys are tracked, so keys chosen by clients (e.g.
+/// usernames) cannot exhaust the memory.
+#[derive(Debug)]
+pub struct Throttle {
+    capacity: usize,
+    tracked: Mutex<Tracked>,
+}
+
+impl Throttle {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tracked: Mutex::new(Tracked::default()),
+        }
+    }
+
+    fn is_forgotten(failures: &Failures, backoff: &Backoff, now: Instant) -> bool {
+        now.duration_since(failures.last) >= backoff.lockout(failures.count) + backoff.max
+    }
+
+    /// Returns how long `key` is still locked out, if at all.
+    pub fn locked_out(&self, key: &str, backoff: &Backoff, now: Instant) -> Option<Duration> {
+        let tracked = self.tracked.lock().unwrap();
+        let failures = tracked.failures.get(key)?;
+
+        (failures.last + backoff.lockout(failures.count))
+            .checked_duration_since(now)
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// Records a failed attempt for `key` and returns the lockout it triggers, if any.
+    pub fn fail(&self, key: &str, backoff: &Backoff, now: Instant) -> Option<Duration> {
+        let mut tracked = self.tracked.lock().unwrap();
+        let sequence = tracked.next_sequence;
+        tracked.next_sequence += 1;
+
+        let previous = tracked.failures.get(key).map(|failures| failures.sequence);
+
+        match previous {
+            Some(previous) => {
+                let key = tracked.order.remove(&previous).unwrap();
+                tracked.order.insert(sequence, key);
+            }
+            None => {
+                while tracked.failures.len() >= self.capacity.max(1) {
+                    let (_, oldest) = tracked.order.pop_first().unwrap();
+                    tracked.failures.remove(&oldest);
+                }
+
+                tracked.order.insert(sequence, key.to_owned());
+            }
+        }
+
+        let failures = tracked.failures.entry(key.to_owned()).or_insert(Failures {
+            count: 0,
+            last: now,
+            sequence,
+        });
+
+        if Self::is_forgotten(failures, backoff, now) {
+            failures.count = 0;
+        }
+
+        failures.count = failures.count.saturating_add(1);
+        failures.last = now;
+        failures.sequence = sequence;
+
+        Some(backoff.lockout(failures.count)).filter(|lockout| !lockout.is_zero())
+    }
+
+    /// Forgets all failed attempts of `key`.
+    pub fn succeed(&self, key: &str) {
+        self.tracked.lock().unwrap().remove(key);
+    }
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self::new(CAPACITY)
+    }
+}
+
+#[test]
+fn test_backoff() {
+    let backoff = Backoff {
+        threshold: 3,
+        base: Duration::from_secs(2),
+        max: Duration::from_secs(60),
+    };
+
+    assert_eq!(backoff.lockout(0), Duration::ZERO);
+    assert_eq!(backoff.lockout(2), Duration::ZERO);
+    assert_eq!(backoff.lockout(3), Duration::from_secs(2));
+    assert_eq!(backoff.lockout(4), Duration::from_secs(4));
+    assert_eq!(backoff.lockout(7), Duration::from_secs(32));
+    assert_eq!(backoff.lockout(8), Duration::from_secs(60));
+    assert_eq!(backoff.lockout(u32::MAX), Duration::from_secs(60));
+
+    let disabled = Backoff {
+        threshold: 0,
+        ..backoff
+    };
+    assert_eq!(disabled.lockout(u32::MAX), Duration::ZERO);
+}
+
+#[test]
+fn test_throttle() {
+    let backoff = Backoff {
+        threshold: 2,
+        base: Duration::from_secs(10),
+        max: Duration::from_secs(60),
+    };
+    let throttle = Throttle::default();
+    let start = Instant::now();
+    let at = |secs| start + Duration::from_secs(secs);
+
+    assert_eq!(throttle.fail("user", &backoff, at(0)), None);
+    assert_eq!(throttle.locked_out("user", &backoff, at(0)), None);
+
+    assert_eq!(
+        throttle.fail("user", &backoff, at(1)),
+        Some(Duration::from_secs(10))
+    );
+    assert_eq!(
+        throttle.locked_out("user", &backoff, at(5)),
+        Some(Duration::from_secs(6))
+    );
+    assert_eq!(throttle.locked_out("other", &backoff, at(5)), None);
+    assert_eq!(throttle.locked_out("user", &backoff, at(11)), None);
+
+    // Further failures double the lockout
+    assert_eq!(
+        throttle.fail("user", &backoff, at(11)),
+        Some(Duration::from_secs(20))
+    );
+
+    // Failures are forgotten after a quiet period
+    assert_eq!(throttle.fail("user", &backoff, at(91)), None);
+
+    // Successful attempts reset the failures
+    assert_eq!(
+        throttle.fail("user", &backoff, at(92)),
+        Some(Duration::from_secs(10))
+    );
+    throttle.succeed("user");
+    assert_eq!(throttle.locked_out("user", &backoff, at(93)), None);
+    assert_eq!(throttle.fail("user", &backoff, at(93)), None);
+}
+
+#[test]
+fn test_throttle_capacity() {
+    let backoff = Backoff {
+        threshold: 2,
+        base: Duration::from_secs(10),
+        max: Duration::from_secs(60),
+    };
+    let throttle = Throttle::new(2);
+    let now = Instant::now();
+
+    throttle.fail("a", &backoff, now);
+    throttle.fail("b", &backoff, now);
+    throttle.fail("a", &backoff, now);
+
+    // "b" failed least recently and is forgotten first
+    throttle.fail("c", &backoff, now);
+
+    let tracked = throttle.tracked.lock().unwrap();
+    assert_eq!(tracked.failures.len(), 2);
+    assert_eq!(tracked.order.len(), 2);
+    assert_eq!(tracked.failures["a"].count, 2);
+    assert!(!tracked.failures.contains_key("b"));
+    assert!(tracked.failures.contains_key("c"));
+}
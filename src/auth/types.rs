@@ -16,16 +16,41 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use super::{acl::Ruleset, password::PasswordHash};
+use super::{
+    acl::{Ruleset, Subject},
+    credential_cache::CredentialCache,
+    network::Network,
+    password::PasswordHash,
+};
+use crate::date::Date;
 use regex::Regex;
-use serde::{de::Visitor, Deserialize, Deserializer};
+use serde::{
+    de::{MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use sha2::{Digest, Sha256};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    fmt::Write,
     hash::{Hash, Hasher},
+    net::IpAddr,
+    path::PathBuf,
 };
 
 #[derive(Debug)]
-pub struct PackageName(pub Regex);
+pub struct PackageName(
+    pub Regex,
+    /// The pattern as written, e.g. in `auth.json`.
+    String,
+);
+
+impl PackageName {
+    /// The number of characters of the pattern other than `*`, the more
+    /// literal characters, the more specific the pattern.
+    pub fn literal_len(&self) -> usize {
+        self.1.chars().filter(|c| *c != '*').count()
+    }
+}
 
 struct PackageNameVisitor;
 impl<'de> Visitor<'de> for PackageNameVisitor {
@@ -39,12 +64,18 @@ impl<'de> Visitor<'de> for PackageNameVisitor {
     where
         E: serde::de::Error,
     {
+        PackageName::try_from(s).map_err(serde::de::Error::custom)
+    }
+}
+
+impl TryFrom<&str> for PackageName {
+    type Error = regex::Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
         let regex_str = regex::escape(s).replace("\\*", ".*");
         let regex_str = format!("^{regex_str}$");
 
-        let regex = Regex::new(&regex_str).map_err(serde::de::Error::custom)?;
-
-        Ok(PackageName(regex))
+        Ok(PackageName(Regex::new(&regex_str)?, s.to_owned()))
     }
 }
 
@@ -54,6 +85,27 @@ impl<'de> Deserialize<'de> for PackageName {
     }
 }
 
+impl std::fmt::Display for PackageName {
+    /// Formats the pattern the way it was given.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let regex = self.0.as_str();
+        let mut chars = regex[1..regex.len() - 1].chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => chars.next().map_or(Ok(()), |c| f.write_char(c))?,
+                '.' => {
+                    chars.next();
+                    f.write_char('*')?;
+                }
+                c => f.write_char(c)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
 impl Eq for PackageName {}
 impl PartialEq for PackageName {
     fn eq(&self, other: &Self) -> bool {
@@ -68,28 +120,502 @@ impl Hash for PackageName {
 }
 
 type UserName = String;
-type GroupName = String;
-pub type Permissions = HashMap<PackageName, Ruleset>;
+pub type GroupName = String;
+
+/// The package permissions of a user, a group or of everyone.
+///
+/// Package name patterns prefixed with `!` are deny rules: They revoke
+/// access to the matching versions instead of granting it.
+#[derive(Debug, Default)]
+pub struct Permissions {
+    pub allow: HashMap<PackageName, Ruleset>,
+    pub deny: HashMap<PackageName, Ruleset>,
+}
+
+impl Permissions {
+    fn matches<'a>(
+        rules: impl IntoIterator<Item = (&'a PackageName, &'a Ruleset)>,
+        package_id: &str,
+        subject: &Subject,
+    ) -> bool {
+        rules
+            .into_iter()
+            .any(|(name_regex, rule)| name_regex.0.is_match(package_id) && rule.evaluate(*subject))
+    }
+
+    pub fn allows(&self, package_id: &str, subject: &Subject) -> bool {
+        Self::matches(&self.allow, package_id, subject)
+    }
+
+    pub fn denies(&self, package_id: &str, subject: &Subject) -> bool {
+        Self::matches(&self.deny, package_id, subject)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+}
+
+impl TryFrom<HashMap<String, Ruleset>> for Permissions {
+    type Error = regex::Error;
+
+    fn try_from(rules: HashMap<String, Ruleset>) -> Result<Self, Self::Error> {
+        let mut permissions = Permissions::default();
+
+        for (pattern, ruleset) in rules {
+            let (target, pattern) = match pattern.strip_prefix('!') {
+                Some(pattern) => (&mut permissions.deny, pattern),
+                None => (&mut permissions.allow, pattern.as_str()),
+            };
+
+            target.insert(PackageName::try_from(pattern)?, ruleset);
+        }
+
+        Ok(permissions)
+    }
+}
+
+impl<'de> Deserialize<'de> for Permissions {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        HashMap::<String, Ruleset>::deserialize(deserializer)?
+            .try_into()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Limits the number of downloads of a user within a rolling window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct DownloadQuota {
+    pub downloads: u32,
+
+    /// The length of the window in seconds.
+    pub window: u64,
+}
+
+/// A group, either given as its plain package permissions or as an object
+/// with the `groups` it includes, its own `packages` permissions and its
+/// `download_quota`.
+///
+/// The two forms may not be mixed, as the keys of the latter would otherwise
+/// be indistinguishable from package name patterns.
+#[derive(Debug, Default)]
+pub struct GroupInfo {
+    /// The groups whose permissions are included in this group.
+    pub groups: Vec<GroupName>,
+
+    pub packages: Permissions,
+
+    /// The default download quota of the group’s members.
+    pub download_quota: Option<DownloadQuota>,
+}
+
+struct GroupInfoVisitor;
+
+impl<'de> Visitor<'de> for GroupInfoVisitor {
+    type Value = GroupInfo;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a valid group")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut groups = None;
+        let mut packages = None;
+        let mut download_quota = None;
+        let mut rules = HashMap::<String, Ruleset>::new();
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "groups" => groups = Some(map.next_value()?),
+                "packages" => packages = Some(map.next_value()?),
+                "download_quota" => download_quota = Some(map.next_value()?),
+                _ => {
+                    rules.insert(key, map.next_value()?);
+                }
+            }
+        }
+
+        if (groups.is_some() || packages.is_some() || download_quota.is_some()) && !rules.is_empty()
+        {
+            return Err(serde::de::Error::custom(
+                "package rules must be placed within `packages` if a group uses `groups`, `packages` or `download_quota`",
+            ));
+        }
+
+        let packages = match packages {
+            Some(packages) => packages,
+            None => Permissions::try_from(rules).map_err(serde::de::Error::custom)?,
+        };
+
+        Ok(GroupInfo {
+            groups: groups.unwrap_or_default(),
+            packages,
+            download_quota,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for GroupInfo {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(GroupInfoVisitor)
+    }
+}
+
+/// Returns whether a version released on `released` is covered by a licence
+/// that is valid until (and including) `valid_until`.
+///
+/// Versions with an unknown release date are only covered by unlimited licences.
+pub fn is_covered(valid_until: Option<Date>, released: Option<Date>) -> bool {
+    match (valid_until, released) {
+        (None, _) => true,
+        (Some(valid_until), Some(released)) => released <= valid_until,
+        (Some(_), None) => false,
+    }
+}
+
+/// The membership of a user in a group, either given as the plain group name
+/// or as an object with the group’s `name` and an optional `valid_until` date.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "GroupMembershipDefinition")]
+pub struct GroupMembership {
+    pub name: GroupName,
+
+    /// The group’s grants only apply to versions released until this date.
+    pub valid_until: Option<Date>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GroupMembershipDefinition {
+    Name(GroupName),
+    Limited {
+        name: GroupName,
+        valid_until: Option<Date>,
+    },
+}
+
+impl From<GroupMembershipDefinition> for GroupMembership {
+    fn from(definition: GroupMembershipDefinition) -> Self {
+        match definition {
+            GroupMembershipDefinition::Name(name) => Self {
+                name,
+                valid_until: None,
+            },
+            GroupMembershipDefinition::Limited { name, valid_until } => Self { name, valid_until },
+        }
+    }
+}
+
+/// The SHA-256 digest of an API token, given as a hexadecimal string.
+///
+/// API tokens are random secrets, thus a fast digest is sufficient and allows
+/// looking up the token’s user without knowing the username.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TokenDigest([u8; 32]);
+
+impl TokenDigest {
+    pub fn of(token: &str) -> Self {
+        Self(Sha256::digest(token.as_bytes()).into())
+    }
+}
+
+struct TokenDigestVisitor;
+
+impl<'de> Visitor<'de> for TokenDigestVisitor {
+    type Value = TokenDigest;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a hexadecimal SHA-256 digest")
+    }
+
+    fn visit_str<E>(self, s: &str) -> std::result::Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        super::hex::decode(s)
+            .and_then(|digest| digest.try_into().ok())
+            .map(TokenDigest)
+            .ok_or_else(|| E::invalid_value(serde::de::Unexpected::Str(s), &self))
+    }
+}
+
+impl<'de> Deserialize<'de> for TokenDigest {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(TokenDigestVisitor)
+    }
+}
+
+/// A named API token of a user, accepted instead of the user’s password.
+#[derive(Debug, Deserialize)]
+pub struct ApiToken {
+    pub sha256: TokenDigest,
+
+    /// The token is rejected after this date.
+    #[serde(default)]
+    pub expires: Option<Date>,
+
+    #[serde(default)]
+    pub revoked: bool,
+
+    /// If given, the token only grants the user’s permissions for matching packages.
+    #[serde(default)]
+    pub packages: Option<Vec<PackageName>>,
+}
+
+impl ApiToken {
+    pub fn is_valid(&self, today: Date) -> bool {
+        !self.revoked && self.expires.is_none_or(|expires| today <= expires)
+    }
+
+    pub fn is_in_scope(&self, package_id: &str) -> bool {
+        self.packages.as_ref().is_none_or(|packages| {
+            packages
+                .iter()
+                .any(|name_regex| name_regex.0.is_match(package_id))
+        })
+    }
+}
+
+/// A licence of a user, e.g. of a single purchase, that is accepted with its
+/// serial key instead of the user’s name and password.
+#[derive(Debug, Deserialize)]
+pub struct Licence {
+    pub serial_key: Box<dyn PasswordHash>,
+
+    /// The licence’s grants only apply to versions released until this date.
+    #[serde(default)]
+    pub valid_until: Option<Date>,
+
+    /// Granted in addition to the user’s permissions.
+    #[serde(default)]
+    pub packages: Permissions,
+}
 
 #[derive(Debug, Deserialize)]
 pub struct UserInfo {
     pub passwd: Box<dyn PasswordHash>,
 
+    /// The user’s and the user’s groups’ grants only apply to versions
+    /// released until this date.
     #[serde(default)]
-    pub groups: Vec<GroupName>,
+    pub valid_until: Option<Date>,
+
+    #[serde(default)]
+    pub groups: Vec<GroupMembership>,
+
+    #[serde(default)]
+    pub packages: Permissions,
+
+    #[serde(default)]
+    pub tokens: HashMap<String, ApiToken>,
+
+    /// The user’s licences by their licence number.
+    #[serde(default)]
+    pub licences: HashMap<String, Licence>,
+
+    /// Overrides the default download quota of the user’s groups.
+    #[serde(default)]
+    pub download_quota: Option<DownloadQuota>,
+}
+
+/// Package permissions granted to clients from the given networks,
+/// in addition to the permissions of the user.
+#[derive(Debug, Deserialize)]
+pub struct NetworkInfo {
+    pub addresses: Vec<Network>,
 
     #[serde(default)]
     pub packages: Permissions,
 }
 
+impl NetworkInfo {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.addresses.iter().any(|network| network.contains(ip))
+    }
+}
+
+/// An Apache htpasswd file whose users are imported, either given as the
+/// plain path or as an object with the `path` and the `groups` its users
+/// are members of. Relative paths are relative to the package directory.
+#[derive(Debug, Deserialize)]
+#[serde(from = "HtpasswdFileDefinition")]
+pub struct HtpasswdFile {
+    pub path: PathBuf,
+
+    pub groups: Vec<GroupMembership>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HtpasswdFileDefinition {
+    Path(PathBuf),
+    WithGroups {
+        path: PathBuf,
+        #[serde(default)]
+        groups: Vec<GroupMembership>,
+    },
+}
+
+impl From<HtpasswdFileDefinition> for HtpasswdFile {
+    fn from(definition: HtpasswdFileDefinition) -> Self {
+        match definition {
+            HtpasswdFileDefinition::Path(path) => Self {
+                path,
+                groups: Vec::new(),
+            },
+            HtpasswdFileDefinition::WithGroups { path, groups } => Self { path, groups },
+        }
+    }
+}
+
+/// Which versions of a package are listed to a user.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    /// All versions are listed, including the ones the user may not access.
+    #[default]
+    All,
+    /// Only the versions the user may access are listed, packages
+    /// without any accessible version are left out entirely.
+    Accessible,
+}
+
+fn deserialize_visibility<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<(PackageName, Visibility)>, D::Error> {
+    HashMap::<String, Visibility>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(pattern, visibility)| {
+            PackageName::try_from(pattern.as_str())
+                .map(|pattern| (pattern, visibility))
+                .map_err(serde::de::Error::custom)
+        })
+        .collect()
+}
+
 #[derive(Debug, Default, Deserialize)]
 pub struct AuthData {
     #[serde(default)]
     pub users: HashMap<UserName, UserInfo>,
 
     #[serde(default)]
-    pub groups: HashMap<GroupName, Permissions>,
+    pub groups: HashMap<GroupName, GroupInfo>,
 
     #[serde(default)]
     pub packages: Permissions,
+
+    #[serde(default)]
+    pub networks: HashMap<String, NetworkInfo>,
+
+    /// The visibility of the packages matching the patterns, see [`AuthData::visibility`].
+    #[serde(default, deserialize_with = "deserialize_visibility")]
+    pub visibility: Vec<(PackageName, Visibility)>,
+
+    #[serde(default)]
+    pub htpasswd: Vec<HtpasswdFile>,
+
+    #[serde(skip)]
+    pub credential_cache: CredentialCache,
+
+    /// The user and the name of every API token by its digest, built when
+    /// the auth data is validated.
+    #[serde(skip)]
+    pub tokens: HashMap<TokenDigest, (UserName, String)>,
+
+    /// The user of every licence by its number, built when the auth data
+    /// is validated.
+    #[serde(skip)]
+    pub licences: HashMap<String, UserName>,
+
+    /// Distinguishes the auth data from previously loaded auth data, so that
+    /// caches outliving it can tell their entries apart. Assigned when the
+    /// auth data is validated, zero for auth data that was never validated.
+    #[serde(skip)]
+    pub generation: u64,
+}
+
+impl AuthData {
+    /// Returns the group called `name` followed by all groups it includes,
+    /// directly or transitively, together with their names. Every group
+    /// is returned only once.
+    pub fn resolve_group<'a>(&'a self, name: &'a str) -> Vec<(&'a str, &'a GroupInfo)> {
+        let mut seen = HashSet::new();
+        let mut pending = vec![name];
+        let mut groups = Vec::new();
+
+        while let Some(name) = pending.pop() {
+            if !seen.insert(name) {
+                continue;
+            }
+
+            if let Some(group) = self.groups.get(name) {
+                groups.push((name, group));
+                pending.extend(group.groups.iter().rev().map(String::as_str));
+            }
+        }
+
+        groups
+    }
+
+    /// Returns the download quota of `user`: The user’s own quota or else the
+    /// quota of the first of the user’s groups (in the order of membership,
+    /// followed by the groups they include) that has one.
+    pub fn download_quota(&self, user: &UserInfo) -> Option<DownloadQuota> {
+        user.download_quota.or_else(|| {
+            user.groups.iter().find_map(|membership| {
+                self.resolve_group(&membership.name)
+                    .into_iter()
+                    .find_map(|(_, group)| group.download_quota)
+            })
+        })
+    }
+
+    /// Returns the visibility of a package: The visibility of the most specific
+    /// matching pattern, i.e. the one with the most characters other than `*`
+    /// (see [`PackageName::literal_len`]), thus `*` sets the default for all
+    /// packages. Of equally specific patterns `accessible` wins, all versions
+    /// are listed if no pattern matches.
+    pub fn visibility(&self, package_id: &str) -> Visibility {
+        self.visibility
+            .iter()
+            .filter(|(pattern, _)| pattern.0.is_match(package_id))
+            .max_by_key(|(pattern, visibility)| {
+                (pattern.literal_len(), *visibility == Visibility::Accessible)
+            })
+            .map(|(_, visibility)| *visibility)
+            .unwrap_or_default()
+    }
+
+    /// Returns the networks `ip` is part of, ordered by their names.
+    pub fn networks_of(&self, ip: IpAddr) -> Vec<(&str, &NetworkInfo)> {
+        let mut networks = self
+            .networks
+            .iter()
+            .filter(|(_, network)| network.contains(ip))
+            .map(|(name, network)| (name.as_str(), network))
+            .collect::<Vec<_>>();
+
+        networks.sort_by_key(|(name, _)| *name);
+
+        networks
+    }
+
+    /// Returns the user and the licence with the given licence number.
+    pub fn find_licence(&self, number: &str) -> Option<(&str, &Licence)> {
+        let username = self.licences.get(number)?;
+        let licence = self.users.get(username)?.licences.get(number)?;
+
+        Some((username, licence))
+    }
+
+    /// Returns the user and the name of the API token with the given digest.
+    pub fn find_token(&self, digest: &TokenDigest) -> Option<(&str, &str, &ApiToken)> {
+        let (username, name) = self.tokens.get(digest)?;
+        let token = self.users.get(username)?.tokens.get(name)?;
+
+        Some((username, name, token))
+    }
 }
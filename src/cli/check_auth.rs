@@ -0,0 +1,66 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::{
+    auth::{acl::Subject, check_auth_data, read_auth_data},
+    package::list_reader::scan_packages,
+    SETTINGS,
+};
+
+/// Reports mistakes in the auth data with regard to the packages of the
+/// package directory and exits with a non-zero status if there are any.
+pub fn run(mut args: impl Iterator<Item = String>) -> crate::Result<()> {
+    if let Some(arg) = args.next() {
+        return Err(format!("Unexpected argument “{arg}”").into());
+    }
+
+    let auth_data = read_auth_data(&SETTINGS.package_dir)?;
+    let package_list = scan_packages()?;
+
+    let packages = package_list
+        .packages
+        .iter()
+        .map(|versions| {
+            let subjects = versions
+                .iter()
+                .map(|package| Subject {
+                    version: &package.data.package_information.version,
+                    released: package.released,
+                })
+                .collect();
+
+            (versions[0].data.name.as_str(), subjects)
+        })
+        .collect::<Vec<_>>();
+
+    let problems = check_auth_data(&auth_data, &packages);
+
+    for problem in &problems {
+        println!("{problem}");
+    }
+
+    if problems.is_empty() {
+        eprintln!("No problems found.");
+
+        Ok(())
+    } else {
+        eprintln!("{} problem(s) found.", problems.len());
+
+        std::process::exit(1)
+    }
+}
@@ -0,0 +1,75 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::{
+    http::helpers::sign_download_link, package::list_reader::scan_packages, version::Version,
+    SETTINGS,
+};
+use std::net::IpAddr;
+
+const USAGE: &str =
+    "Usage: download-link <package> <version> [--valid-for <seconds>] [--ip <address>]";
+
+/// Prints a signed link to download a version of a package without credentials.
+pub fn run(mut args: impl Iterator<Item = String>) -> crate::Result<()> {
+    let (Some(package_id), Some(version)) = (args.next(), args.next()) else {
+        return Err(USAGE.into());
+    };
+
+    let mut valid_for = None;
+    let mut ip = None;
+
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--valid-for", Some(value)) => {
+                valid_for = Some(
+                    value
+                        .parse::<u64>()
+                        .map_err(|_| format!("Invalid duration “{value}”"))?,
+                )
+            }
+            ("--ip", Some(value)) => {
+                ip = Some(
+                    value
+                        .parse::<IpAddr>()
+                        .map_err(|_| format!("Invalid IP address “{value}”"))?,
+                )
+            }
+            _ => return Err(USAGE.into()),
+        }
+    }
+
+    let host = SETTINGS
+        .host
+        .as_ref()
+        .ok_or("The host must be configured to create download links")?;
+
+    let version =
+        Version::try_from(version.as_str()).map_err(|_| format!("Invalid version “{version}”"))?;
+
+    if scan_packages()?.find(&package_id, &version).is_none() {
+        return Err(format!("{package_id} {version} is not hosted").into());
+    }
+
+    let path = sign_download_link(&package_id, &version, valid_for, ip)
+        .ok_or("Signed download links are disabled, no download_link_secret is configured")?;
+
+    println!("{host}{path}");
+
+    Ok(())
+}
@@ -0,0 +1,56 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::{
+    auth::read_auth_data, http::helpers::explain_hosted_access,
+    package::list_reader::scan_packages, version::Version, SETTINGS,
+};
+
+/// Explains why a user may or may not access a version of a package.
+///
+/// Usage: `explain <package> <version> [<user>]`, anonymous users are
+/// assumed if no user is given.
+pub fn run(mut args: impl Iterator<Item = String>) -> crate::Result<()> {
+    let (Some(package_id), Some(version)) = (args.next(), args.next()) else {
+        return Err("Usage: explain <package> <version> [<user>]".into());
+    };
+    let username = args.next();
+
+    if let Some(arg) = args.next() {
+        return Err(format!("Unexpected argument “{arg}”").into());
+    }
+
+    let version =
+        Version::try_from(version.as_str()).map_err(|_| format!("Invalid version “{version}”"))?;
+
+    let auth_data = read_auth_data(&SETTINGS.package_dir)?;
+    let package_list = scan_packages()?;
+
+    let explanation = explain_hosted_access(
+        &package_list,
+        &auth_data,
+        username.as_deref(),
+        &package_id,
+        &version,
+    )
+    .ok_or_else(|| format!("{package_id} {version} is not hosted"))?;
+
+    print!("{explanation}");
+
+    Ok(())
+}
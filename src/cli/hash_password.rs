@@ -0,0 +1,136 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::auth::{hash_password, PasswordHash, DEFAULT_BCRYPT_COST, HASH_ALGORITHMS};
+use serde::{
+    de::{value::StrDeserializer, IntoDeserializer},
+    Deserialize,
+};
+use std::io::{BufRead, IsTerminal};
+
+const USAGE: &str = "Usage: hash-password [--algorithm <algorithm>] [--cost <cost>]";
+
+/// Disables echoing the input of the terminal on stdin until dropped.
+struct NoEcho(libc::termios);
+
+impl NoEcho {
+    fn new() -> Option<Self> {
+        unsafe {
+            let mut termios = std::mem::zeroed::<libc::termios>();
+
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                return None;
+            }
+
+            let original = termios;
+            termios.c_lflag &= !libc::ECHO;
+
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios) != 0 {
+                return None;
+            }
+
+            Some(Self(original))
+        }
+    }
+}
+
+impl Drop for NoEcho {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.0);
+        }
+    }
+}
+
+/// Reads a single line from stdin, prompting for it without echo if stdin is a terminal.
+fn read_password(prompt: &str) -> crate::Result<String> {
+    let stdin = std::io::stdin();
+    let no_echo = if stdin.is_terminal() {
+        eprint!("{prompt}: ");
+        NoEcho::new()
+    } else {
+        None
+    };
+
+    let mut password = String::new();
+    stdin.lock().read_line(&mut password)?;
+
+    if no_echo.is_some() {
+        eprintln!();
+    }
+
+    let password = password
+        .strip_suffix('\n')
+        .map(|password| password.strip_suffix('\r').unwrap_or(password))
+        .unwrap_or(&password);
+
+    Ok(password.to_owned())
+}
+
+/// Reads a password from stdin and prints its hash in the format expected
+/// by `auth.json`, after verifying that the hash is accepted and matches.
+pub fn run(mut args: impl Iterator<Item = String>) -> crate::Result<()> {
+    let mut algorithm = HASH_ALGORITHMS[0].to_owned();
+    let mut cost = None;
+
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--algorithm", Some(value)) => algorithm = value.to_ascii_lowercase(),
+            ("--cost", Some(value)) => {
+                cost = Some(
+                    value
+                        .parse::<u32>()
+                        .map_err(|_| format!("Invalid cost “{value}”"))?,
+                )
+            }
+            _ => return Err(USAGE.into()),
+        }
+    }
+
+    let password = read_password("Password")?;
+
+    if password.is_empty() {
+        return Err("The password must not be empty".into());
+    }
+
+    if std::io::stdin().is_terminal() && read_password("Repeat password")? != password {
+        return Err("The passwords do not match".into());
+    }
+
+    if algorithm == "bcrypt" {
+        eprintln!(
+            "Hashing with bcrypt, cost {}",
+            cost.unwrap_or(DEFAULT_BCRYPT_COST)
+        );
+    } else {
+        eprintln!("Hashing with {algorithm}");
+    }
+
+    let hash = hash_password(&algorithm, &password, cost)?;
+
+    let deserializer: StrDeserializer<serde::de::value::Error> = hash.as_str().into_deserializer();
+    let parsed = Box::<dyn PasswordHash>::deserialize(deserializer)?;
+
+    if parsed.unknown_hash().is_some() || !parsed.verify(&password) {
+        return Err("The generated hash does not verify the password".into());
+    }
+
+    println!("{hash}");
+
+    Ok(())
+}
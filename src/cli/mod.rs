@@ -0,0 +1,39 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Subcommands for maintaining the package directory, the server is
+//! started if no subcommand is given.
+
+mod check_auth;
+mod download_link;
+mod explain;
+mod hash_password;
+
+/// Runs the subcommand `command` with the remaining command line arguments.
+pub fn run(command: &str, args: impl Iterator<Item = String>) -> crate::Result<()> {
+    match command {
+        "check-auth" => check_auth::run(args),
+        "download-link" => download_link::run(args),
+        "explain" => explain::run(args),
+        "hash-password" => hash_password::run(args),
+        _ => Err(format!(
+            "Unknown command “{command}”, expected one of: check-auth, download-link, explain, hash-password"
+        )
+        .into()),
+    }
+}
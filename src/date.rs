@@ -0,0 +1,173 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::nom::numeric;
+use nom::{
+    character::complete::char,
+    combinator::{eof, verify},
+    sequence::terminated,
+    IResult, Parser,
+};
+use serde::{de::Visitor, Deserialize, Deserializer};
+use std::{fmt::Display, time::SystemTime};
+
+/// A calendar date (`YYYY-MM-DD`) in UTC.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Copy)]
+pub struct Date {
+    year: u16,
+    month: u8,
+    day: u8,
+}
+
+impl Date {
+    pub fn new(year: u16, month: u8, day: u8) -> Self {
+        Self { year, month, day }
+    }
+
+    pub fn parser(input: &str) -> IResult<&str, Date> {
+        parser(input)
+    }
+}
+
+impl Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl<'a> TryFrom<&'a str> for Date {
+    type Error = nom::Err<nom::error::Error<&'a str>>;
+
+    fn try_from(other: &'a str) -> Result<Self, Self::Error> {
+        terminated(parser, eof).parse(other).map(|(rest, date)| {
+            assert!(rest.is_empty());
+
+            date
+        })
+    }
+}
+
+impl From<SystemTime> for Date {
+    /// Converts the time into the UTC calendar date it falls on.
+    fn from(time: SystemTime) -> Self {
+        let secs = match time.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs() as i64,
+            Err(err) => -(err.duration().as_secs() as i64),
+        };
+
+        // See <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+        let days = secs.div_euclid(86_400) + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+        Self::new(year.clamp(0, 9999) as u16, month as u8, day as u8)
+    }
+}
+
+struct DateVisitor;
+
+impl<'de> Visitor<'de> for DateVisitor {
+    type Value = Date;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a valid date (YYYY-MM-DD)")
+    }
+
+    fn visit_str<E>(self, s: &str) -> std::result::Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Date::try_from(s).map_err(serde::de::Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for Date {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(DateVisitor)
+    }
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Parses valid dates.
+fn parser(input: &str) -> IResult<&str, Date> {
+    let (input, year) = verify(numeric, |year: &u16| *year <= 9999).parse(input)?;
+    let (input, _) = char('-')(input)?;
+    let (input, month) = verify(numeric, |month: &u8| (1..=12).contains(month)).parse(input)?;
+    let (input, _) = char('-')(input)?;
+    let (input, day) = verify(numeric, |day: &u8| {
+        (1..=days_in_month(year, month)).contains(day)
+    })
+    .parse(input)?;
+
+    Ok((input, Date { year, month, day }))
+}
+
+#[test]
+fn test_parser() {
+    assert_eq!(parser("2025-06-01"), Ok(("", Date::new(2025, 6, 1))));
+    assert_eq!(parser("2025-6-1"), Ok(("", Date::new(2025, 6, 1))));
+    assert_eq!(parser("2025-12-31 "), Ok((" ", Date::new(2025, 12, 31))));
+
+    assert!(parser("2025-13-01").is_err());
+    assert!(parser("2025-00-01").is_err());
+    assert!(parser("2025-01-32").is_err());
+    assert!(parser("2025-02-29").is_err());
+    assert!(parser("2025-02-31").is_err());
+    assert!(parser("2025-04-31").is_err());
+    assert!(parser("1900-02-29").is_err());
+    assert_eq!(parser("2024-02-29"), Ok(("", Date::new(2024, 2, 29))));
+    assert_eq!(parser("2000-02-29"), Ok(("", Date::new(2000, 2, 29))));
+    assert_eq!(parser("2025-04-30"), Ok(("", Date::new(2025, 4, 30))));
+    assert!(parser("2025.01.01").is_err());
+    assert!(parser("2025-01").is_err());
+    assert!(Date::try_from("2025-01-01T00:00:00Z").is_err());
+}
+
+#[test]
+fn test_from_system_time() {
+    use std::time::Duration;
+
+    assert_eq!(Date::from(SystemTime::UNIX_EPOCH), Date::new(1970, 1, 1));
+    assert_eq!(
+        Date::from(SystemTime::UNIX_EPOCH + Duration::from_secs(951_782_400)),
+        Date::new(2000, 2, 29)
+    );
+    assert_eq!(
+        Date::from(SystemTime::UNIX_EPOCH + Duration::from_secs(1_748_822_399)),
+        Date::new(2025, 6, 1)
+    );
+    assert!(Date::new(2025, 6, 1) < Date::new(2025, 6, 2));
+    assert!(Date::new(2024, 12, 31) < Date::new(2025, 1, 1));
+}
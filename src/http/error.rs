@@ -16,7 +16,7 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use crate::{fluent, http::header::Language, version::Version};
+use crate::{fluent, http::header::Language, package::list_reader::PurchaseInfo, version::Version};
 use actix_web::{
     http::{
         header,
@@ -27,6 +27,7 @@ use actix_web::{
 };
 use actix_web_httpauth::headers::www_authenticate::{basic::Basic as Challenge, WwwAuthenticate};
 use std::fmt::Display;
+use url::Url;
 
 #[derive(Debug)]
 pub enum Error {
@@ -48,10 +49,17 @@ pub enum Error {
     // Failed to read a known package from the file system
     PackageReadFailed(HttpRequest, String),
 
-    PaymentRequired(HttpRequest, String, Version),
+    // 402 - The user may not access the package, optionally telling where to buy it
+    PaymentRequired(HttpRequest, String, Version, Option<Box<PurchaseInfo>>),
 
     AccessDenied(HttpRequest),
 
+    // 429 - Locked out after too many failed logins, may retry after the given number of seconds
+    TooManyLoginFailures(HttpRequest, u64),
+
+    // 429 - The user’s download quota is exhausted, may retry after the given number of seconds
+    DownloadQuotaExceeded(HttpRequest, u64),
+
     IoError(HttpRequest, std::io::Error),
 }
 
@@ -65,6 +73,8 @@ impl Error {
             | Self::UnknownPackageVersion(ref req, ..)
             | Self::PackageReadFailed(ref req, ..)
             | Self::PaymentRequired(ref req, ..)
+            | Self::TooManyLoginFailures(ref req, ..)
+            | Self::DownloadQuotaExceeded(ref req, ..)
             | Self::PackageListUnavailable(ref req)
             | Self::IoError(ref req, ..) => req,
         }
@@ -93,8 +103,35 @@ impl Display for Error {
             Self::PackageReadFailed(_, ref file) => {
                 fluent!(lang, "package-read-failed", { file })
             }
-            Self::PaymentRequired(_, ref package_id, version) => {
-                fluent!(lang, "package-payment-required", { package_id, "version": version.to_string() })
+            Self::PaymentRequired(_, ref package_id, version, ref purchase_info) => {
+                let mut message = fluent!(lang, "package-payment-required", { package_id, "version": version.to_string() });
+
+                if let Some(info) = purchase_info {
+                    let details = [
+                        (
+                            fluent!(lang, "purchase-store"),
+                            info.store_url.as_ref().map(Url::as_str),
+                        ),
+                        (fluent!(lang, "purchase-price"), info.price.as_deref()),
+                        (fluent!(lang, "purchase-contact"), info.contact.as_deref()),
+                    ];
+
+                    message.push('\n');
+
+                    for (label, value) in details {
+                        if let Some(value) = value {
+                            message.push_str(&format!("\n{}: {}", label, value));
+                        }
+                    }
+                }
+
+                message
+            }
+            Self::TooManyLoginFailures(_, seconds) => {
+                fluent!(lang, "too-many-login-failures", { "seconds": seconds.to_string() })
+            }
+            Self::DownloadQuotaExceeded(_, seconds) => {
+                fluent!(lang, "download-quota-exceeded", { "seconds": seconds.to_string() })
             }
             Self::PackageListUnavailable(..) => fluent!(lang, "package-list-unavailable"),
             Self::IoError(..) => panic!("Not implemented"),
@@ -115,6 +152,9 @@ impl ResponseError for Error {
             Self::UnknownPackageVersion(..) => StatusCode::NOT_FOUND,
             Self::PackageReadFailed(..) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::PaymentRequired(..) => StatusCode::PAYMENT_REQUIRED,
+            Self::TooManyLoginFailures(..) | Self::DownloadQuotaExceeded(..) => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
 
             Self::PackageListUnavailable(..) => StatusCode::SERVICE_UNAVAILABLE,
             Self::IoError(_, e) => e.status_code(),
@@ -140,7 +180,15 @@ impl ResponseError for Error {
                 .body(fluent!(lang, "access-denied"));
         }
 
-        HttpResponseBuilder::new(self.status_code())
+        let mut builder = HttpResponseBuilder::new(self.status_code());
+
+        if let Self::TooManyLoginFailures(_, seconds) | Self::DownloadQuotaExceeded(_, seconds) =
+            self
+        {
+            builder.insert_header((header::RETRY_AFTER, *seconds));
+        }
+
+        builder
             .insert_header((header::CONTENT_TYPE, "text/plain; charset=utf-8"))
             .insert_header(CacheControl(vec![
                 CacheDirective::NoCache,
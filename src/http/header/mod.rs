@@ -42,6 +42,14 @@ pub fn negotiate_language(requested: &[&LanguageIdentifier]) -> LanguageIdentifi
     (*supported.first().unwrap()).clone()
 }
 
+impl Language {
+    /// The language without region or script, the translations in a
+    /// `package.xml` are usually only tagged with it.
+    pub fn package_language(&self) -> LanguageIdentifier {
+        LanguageIdentifier::from_parts(self.language, None, None, &[])
+    }
+}
+
 impl std::ops::Deref for Language {
     type Target = LanguageIdentifier;
 
@@ -16,78 +16,757 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use super::error::Error;
 use crate::{
-    auth::{AuthData, Permissions},
+    audit::{self, Outcome, Record},
+    auth::{
+        acl::{Ruleset, Subject},
+        authenticator::{self, Authenticator, Credentials},
+        is_covered, AuthData, Backoff, DownloadLink, Network, PackageName, Permissions, Throttle,
+        UserInfo, Visibility,
+    },
+    date::Date,
+    package::list_reader::{PackageInfo, PackageList},
     version::Version,
+    SETTINGS,
 };
 use actix_web::{
-    http::{header::LOCATION, StatusCode},
-    HttpResponse,
+    http::{
+        header::{Header, LOCATION, X_FORWARDED_FOR},
+        StatusCode,
+    },
+    HttpRequest, HttpResponse,
+};
+use actix_web_httpauth::{
+    extractors::basic::BasicAuth,
+    headers::authorization::{Authorization, Bearer},
+};
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
 };
-use actix_web_httpauth::extractors::basic::BasicAuth;
 
+/// Returns whether the given version of a package may be accessed.
+///
+/// The permissions are consulted from the most to the least specific source,
+/// the first matching rule decides:
+///
+/// 1. The deny rules of the licence the user authenticated with.
+/// 2. The allow rules of the licence the user authenticated with.
+/// 3. The user’s deny rules.
+/// 4. The user’s allow rules.
+/// 5. The deny rules of any of the user’s groups and the groups they include.
+/// 6. The allow rules of any of the user’s groups and the groups they include.
+/// 7. The deny rules of any of the networks the client is part of.
+/// 8. The allow rules of any of the networks the client is part of.
+/// 9. The global deny rules.
+/// 10. The global allow rules.
+///
+/// A deny rule thus only revokes grants of less specific sources: A group’s
+/// `"!be.bastelstu.example": "*"` revokes a global `"be.bastelstu.example": "*"`
+/// for the group’s members, but a user’s own `"be.bastelstu.example": "*"`
+/// still grants access. Within the same source a deny rule always wins,
+/// even over `*`.
+///
+/// The allow rules of a licence, of a user and of the user’s groups are ignored
+/// for versions released after the licence’s, the user’s or the group
+/// membership’s `valid_until` date.
 pub fn is_accessible(
     package_id: &str,
     version: &Version,
+    released: Option<Date>,
     auth_info: &AuthInfo,
     auth_data: &AuthData,
 ) -> bool {
-    let check_permissions = |permissions: &Permissions| -> bool {
-        for (name_regex, rule) in permissions {
-            if name_regex.0.is_match(package_id) && rule.evaluate(version) {
-                return true;
-            }
+    check_access(
+        package_id,
+        version,
+        released,
+        auth_info,
+        auth_data,
+        &mut |_| (),
+    )
+}
+
+/// Returns whether the given version of a package is listed to the user:
+/// Either the package’s [`Visibility`] lists all versions or the version
+/// is accessible, see [`is_accessible`].
+pub fn is_listed(
+    package_id: &str,
+    version: &Version,
+    released: Option<Date>,
+    auth_info: &AuthInfo,
+    auth_data: &AuthData,
+) -> bool {
+    auth_data.visibility(package_id) == Visibility::All
+        || is_accessible(package_id, version, released, auth_info, auth_data)
+}
+
+/// Returns the versions of every package that are listed to the user,
+/// leaving out packages without any listed version.
+pub fn listed_packages<'a>(
+    package_list: &'a PackageList,
+    auth_info: &AuthInfo,
+    auth_data: &AuthData,
+) -> Vec<Vec<&'a PackageInfo>> {
+    package_list
+        .packages
+        .iter()
+        .map(|versions| {
+            versions
+                .iter()
+                .filter(|package| {
+                    is_listed(
+                        &package.data.name,
+                        &package.data.package_information.version,
+                        package.released,
+                        auth_info,
+                        auth_data,
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+        .filter(|versions| !versions.is_empty())
+        .collect()
+}
+
+/// Where the permissions consulted by [`is_accessible`] are defined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source<'a> {
+    Licence(&'a str),
+    User(&'a str),
+    Group(&'a str),
+    Network(&'a str),
+    Everyone,
+}
+
+impl Display for Source<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Licence(licence) => write!(f, "licence “{licence}”"),
+            Source::User(user) => write!(f, "user “{user}”"),
+            Source::Group(group) => write!(f, "group “{group}”"),
+            Source::Network(network) => write!(f, "network “{network}”"),
+            Source::Everyone => write!(f, "global"),
+        }
+    }
+}
+
+/// The allow or deny rules of a source consulted by [`is_accessible`].
+#[derive(Debug)]
+pub struct Consulted<'a> {
+    pub source: Source<'a>,
+    pub deny: bool,
+    pub rules: &'a HashMap<PackageName, Ruleset>,
+
+    /// Whether the version is covered by the `valid_until` date of the user
+    /// or of the group membership, allow rules are ignored otherwise.
+    pub covered: bool,
+}
+
+/// Implements [`is_accessible`], passing the rules to `consult` right before
+/// they are consulted.
+fn check_access<'a>(
+    package_id: &str,
+    version: &Version,
+    released: Option<Date>,
+    auth_info: &'a AuthInfo,
+    auth_data: &'a AuthData,
+    consult: &mut dyn FnMut(Consulted<'a>),
+) -> bool {
+    let subject = Subject { version, released };
+
+    let decide = |consult: &mut dyn FnMut(Consulted<'a>),
+                  source: Source<'a>,
+                  permissions: &'a Permissions,
+                  valid_until: Option<Date>|
+     -> Option<bool> {
+        consult(Consulted {
+            source,
+            deny: true,
+            rules: &permissions.deny,
+            covered: true,
+        });
+
+        if permissions.denies(package_id, &subject) {
+            return Some(false);
         }
 
-        false
+        let covered = is_covered(valid_until, released);
+
+        consult(Consulted {
+            source,
+            deny: false,
+            rules: &permissions.allow,
+            covered,
+        });
+
+        if covered && permissions.allows(package_id, &subject) {
+            Some(true)
+        } else {
+            None
+        }
     };
 
-    // First check the general package rules
-    if check_permissions(&auth_data.packages) {
-        return true;
-    }
+    // First check the user’s permissions, unless the user authenticated
+    // with an API token that is not scoped to this package
+    {
+        if let Some(user_data) = auth_info.user(auth_data).filter(|user_data| {
+            auth_info.token.as_ref().is_none_or(|token| {
+                user_data
+                    .tokens
+                    .get(token)
+                    .is_some_and(|token| token.is_in_scope(package_id))
+            })
+        }) {
+            let username = auth_info.username.as_deref().unwrap_or_default();
+
+            // Check the permissions of the licence the user authenticated with
+            let licence = auth_info
+                .licence
+                .as_deref()
+                .and_then(|number| Some((number, user_data.licences.get(number)?)));
+
+            if let Some((number, licence)) = licence {
+                if let Some(decision) = decide(
+                    consult,
+                    Source::Licence(number),
+                    &licence.packages,
+                    licence.valid_until,
+                ) {
+                    return decision;
+                }
+            }
 
-    // Then check the user’s permissions
-    if let Some(username) = &auth_info.username {
-        if let Some(user_data) = auth_data.users.get(username) {
             // Check the user’s own package permissions
-            if check_permissions(&user_data.packages) {
+            if let Some(decision) = decide(
+                consult,
+                Source::User(username),
+                &user_data.packages,
+                user_data.valid_until,
+            ) {
+                return decision;
+            }
+
+            // Check the user’s groups, including the groups they include
+            let groups = user_data
+                .groups
+                .iter()
+                .flat_map(|membership| {
+                    let valid_until = match (user_data.valid_until, membership.valid_until) {
+                        (Some(a), Some(b)) => Some(a.min(b)),
+                        (a, b) => a.or(b),
+                    };
+
+                    auth_data
+                        .resolve_group(&membership.name)
+                        .into_iter()
+                        .map(move |(name, group)| (name, &group.packages, valid_until))
+                })
+                .collect::<Vec<_>>();
+
+            if groups.iter().any(|(name, group, _)| {
+                consult(Consulted {
+                    source: Source::Group(name),
+                    deny: true,
+                    rules: &group.deny,
+                    covered: true,
+                });
+
+                group.denies(package_id, &subject)
+            }) {
+                return false;
+            }
+
+            if groups.iter().any(|(name, group, valid_until)| {
+                let covered = is_covered(*valid_until, released);
+
+                consult(Consulted {
+                    source: Source::Group(name),
+                    deny: false,
+                    rules: &group.allow,
+                    covered,
+                });
+
+                covered && group.allows(package_id, &subject)
+            }) {
                 return true;
             }
+        }
+    }
 
-            // Check the user’s groups
-            for group in &user_data.groups {
-                if let Some(group) = auth_data.groups.get(group) {
-                    if check_permissions(group) {
-                        return true;
-                    }
+    // Then check the networks the client is part of
+    if let Some(ip) = auth_info.ip {
+        let networks = auth_data.networks_of(ip);
+
+        if networks.iter().any(|(name, network)| {
+            consult(Consulted {
+                source: Source::Network(name),
+                deny: true,
+                rules: &network.packages.deny,
+                covered: true,
+            });
+
+            network.packages.denies(package_id, &subject)
+        }) {
+            return false;
+        }
+
+        if networks.iter().any(|(name, network)| {
+            consult(Consulted {
+                source: Source::Network(name),
+                deny: false,
+                rules: &network.packages.allow,
+                covered: true,
+            });
+
+            network.packages.allows(package_id, &subject)
+        }) {
+            return true;
+        }
+    }
+
+    // Then check the general package rules
+    decide(consult, Source::Everyone, &auth_data.packages, None).unwrap_or(false)
+}
+
+/// A single rule consulted by [`is_accessible`].
+#[derive(Debug)]
+pub struct ExplainedRule<'a> {
+    pub source: Source<'a>,
+    pub deny: bool,
+    pub pattern: &'a PackageName,
+
+    /// How the ruleset evaluated, `None` if the pattern does not match.
+    pub ruleset_matches: Option<bool>,
+    pub covered: bool,
+}
+
+/// Why a version of a package is accessible to a user or not.
+#[derive(Debug)]
+pub struct Explanation<'a> {
+    pub rules: Vec<ExplainedRule<'a>>,
+    pub accessible: bool,
+}
+
+/// Explains the decision of [`is_accessible`], listing every rule
+/// it consulted in the order it consulted them.
+pub fn explain_access<'a>(
+    package_id: &str,
+    version: &Version,
+    released: Option<Date>,
+    auth_info: &'a AuthInfo,
+    auth_data: &'a AuthData,
+) -> Explanation<'a> {
+    let subject = Subject { version, released };
+    let mut rules = Vec::new();
+
+    let accessible = check_access(
+        package_id,
+        version,
+        released,
+        auth_info,
+        auth_data,
+        &mut |consulted| {
+            let mut consulted_rules = consulted.rules.iter().collect::<Vec<_>>();
+            consulted_rules.sort_by_key(|(pattern, _)| pattern.0.as_str());
+
+            rules.extend(consulted_rules.into_iter().map(|(pattern, ruleset)| {
+                let pattern_matches = pattern.0.is_match(package_id);
+
+                ExplainedRule {
+                    source: consulted.source,
+                    deny: consulted.deny,
+                    pattern,
+                    ruleset_matches: pattern_matches.then(|| ruleset.evaluate(subject)),
+                    covered: consulted.covered,
                 }
+            }));
+        },
+    );
+
+    Explanation { rules, accessible }
+}
+
+impl Display for Explanation<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for rule in &self.rules {
+            write!(
+                f,
+                "{}: {}{}: ",
+                rule.source,
+                if rule.deny { "!" } else { "" },
+                rule.pattern
+            )?;
+
+            match rule.ruleset_matches {
+                None => write!(f, "pattern does not match")?,
+                Some(true) => write!(f, "pattern matches, ruleset matches")?,
+                Some(false) => write!(f, "pattern matches, ruleset does not match")?,
+            }
+
+            if !rule.covered {
+                write!(f, " (ignored, released after valid_until)")?;
             }
+
+            writeln!(f)?;
         }
+
+        if self.accessible {
+            writeln!(f, "Access granted.")
+        } else {
+            writeln!(f, "Access denied.")
+        }
+    }
+}
+
+/// Explains whether a user, or an anonymous user if `username` is `None`,
+/// may access a hosted version of a package, see [`explain_access`].
+///
+/// Returns `None` if the version is not hosted.
+pub fn explain_hosted_access(
+    package_list: &PackageList,
+    auth_data: &AuthData,
+    username: Option<&str>,
+    package_id: &str,
+    version: &Version,
+) -> Option<String> {
+    let released = package_list.find(package_id, version)?.released;
+
+    let auth_info = AuthInfo {
+        username: username.map(ToOwned::to_owned),
+        ..Default::default()
+    };
+    let explanation = explain_access(package_id, version, released, &auth_info, auth_data);
+
+    let mut text = match username {
+        Some(username) => format!("Access of user “{username}” to {package_id} {version}"),
+        None => format!("Access of anonymous users to {package_id} {version}"),
+    };
+
+    match released {
+        Some(released) => text.push_str(&format!(" (released {released}):\n")),
+        None => text.push_str(" (release date unknown):\n"),
+    }
+
+    if username.is_some_and(|username| !auth_data.users.contains_key(username)) {
+        text.push_str("The user is unknown, only the global rules apply.\n");
     }
 
-    false
+    text.push_str(&explanation.to_string());
+
+    Some(text)
 }
 
-#[derive(Debug)]
+/// Returns a signed link to download a version of a package, relative to
+/// the package server, or `None` if no `download_link_secret` is configured.
+///
+/// The link is valid for `valid_for` seconds, `download_link_ttl` by default.
+pub fn sign_download_link(
+    package_id: &str,
+    version: &Version,
+    valid_for: Option<u64>,
+    ip: Option<IpAddr>,
+) -> Option<String> {
+    let secret = SETTINGS.download_link_secret.as_ref()?;
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+
+    let link = DownloadLink {
+        package_id,
+        version,
+        expires: now.as_secs() + valid_for.unwrap_or(SETTINGS.download_link_ttl),
+        ip,
+    };
+
+    Some(link.path(secret))
+}
+
+/// Returns a key that differs between users who may see different packages,
+/// e.g. for use in an ETag, or `None` for anonymous users without any network.
+pub fn access_key(auth_info: &AuthInfo, auth_data: &AuthData) -> Option<String> {
+    // Scoped API tokens see a subset of their user’s packages,
+    // licences see additional packages
+    let username =
+        auth_info
+            .username
+            .as_ref()
+            .map(|username| match (&auth_info.token, &auth_info.licence) {
+                (Some(token), _) => format!("{}#{}", username, token),
+                (None, Some(licence)) => format!("{}/{}", username, licence),
+                (None, None) => username.clone(),
+            });
+
+    // Clients see additional packages within some networks
+    let networks = auth_info
+        .ip
+        .map(|ip| auth_data.networks_of(ip))
+        .unwrap_or_default();
+
+    if networks.is_empty() {
+        username
+    } else {
+        let networks = networks.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+
+        Some(format!(
+            "{}@{}",
+            username.unwrap_or_default(),
+            networks.join("+")
+        ))
+    }
+}
+
+/// Returns whether the user authenticated with a password and is a member
+/// of the `admin_group`, directly or through an included group.
+pub fn is_admin(auth_info: &AuthInfo, auth_data: &AuthData) -> bool {
+    let (Some(admin_group), None, None) =
+        (&SETTINGS.admin_group, &auth_info.token, &auth_info.licence)
+    else {
+        return false;
+    };
+
+    auth_info.user(auth_data).is_some_and(|user| {
+        user.groups.iter().any(|membership| {
+            auth_data
+                .resolve_group(&membership.name)
+                .iter()
+                .any(|(name, _)| name == admin_group)
+        })
+    })
+}
+
+#[derive(Debug, Default)]
 pub struct AuthInfo {
     pub username: Option<String>,
+
+    /// The name of the API token the user authenticated with, if any.
+    pub token: Option<String>,
+
+    /// The number of the licence the user authenticated with, if any.
+    pub licence: Option<String>,
+
+    /// The user’s data, if the user is not part of the auth data
+    /// but was provided by an external authenticator.
+    pub user: Option<Arc<UserInfo>>,
+
+    /// The address of the client, see [`client_ip`].
+    pub ip: Option<IpAddr>,
 }
 
-pub fn get_auth_info(auth_data: &AuthData, auth: Option<BasicAuth>) -> AuthInfo {
-    let username = auth.and_then(|auth| {
-        let user_id = auth.user_id();
-        let password = auth.password();
+impl AuthInfo {
+    /// Returns the data of the authenticated user.
+    pub fn user<'a>(&'a self, auth_data: &'a AuthData) -> Option<&'a UserInfo> {
+        match &self.user {
+            Some(user) => Some(user),
+            None => self
+                .username
+                .as_ref()
+                .and_then(|username| auth_data.users.get(username)),
+        }
+    }
+}
 
-        let user = auth_data.users.get(user_id);
+static AUTHENTICATOR: Lazy<Box<dyn Authenticator>> = Lazy::new(authenticator::from_settings);
 
-        user.and_then(|user| match password {
-            Some(password) if user.passwd.verify(password) => Some(user_id.to_owned()),
-            _ => None,
-        })
+static LOGIN_THROTTLE: Lazy<Throttle> = Lazy::new(Throttle::default);
+
+fn login_backoff(threshold: u32, max: u64) -> Backoff {
+    Backoff {
+        threshold,
+        base: Duration::from_secs(SETTINGS.login_lockout_base),
+        max: Duration::from_secs(max),
+    }
+}
+
+/// Returns the address of the client.
+///
+/// Requests of `trusted_proxies` are attributed to the rightmost address
+/// of the `X-Forwarded-For` header that is not a trusted proxy itself.
+pub fn client_ip(req: &HttpRequest) -> Option<IpAddr> {
+    let forwarded_for = req
+        .headers()
+        .get_all(X_FORWARDED_FOR)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect::<Vec<_>>();
+
+    Some(forwarded_client_ip(
+        req.peer_addr()?.ip(),
+        &forwarded_for,
+        &SETTINGS.trusted_proxies,
+    ))
+}
+
+fn forwarded_client_ip(
+    peer: IpAddr,
+    forwarded_for: &[&str],
+    trusted_proxies: &[Network],
+) -> IpAddr {
+    let is_trusted = |ip: IpAddr| trusted_proxies.iter().any(|network| network.contains(ip));
+    let mut ip = peer.to_canonical();
+
+    for forwarded in forwarded_for.iter().rev() {
+        if !is_trusted(ip) {
+            break;
+        }
+
+        match forwarded.trim().parse::<IpAddr>() {
+            Ok(forwarded) => ip = forwarded.to_canonical(),
+            Err(_) => break,
+        }
+    }
+
+    ip
+}
+
+/// Verifies the credentials of the request, if any.
+///
+/// Users authenticate with Basic authentication, using either their password
+/// or one of their API tokens as the password. API tokens may also be given
+/// on their own as `Authorization: Bearer <token>`.
+///
+/// Failed logins are tracked per IP address and per username.
+/// Once either has failed too often, further logins are rejected with
+/// `TooManyLoginFailures` until the lockout has passed, without verifying
+/// the credentials. Failures and lockouts are logged in a stable format
+/// that is suitable for tools like fail2ban:
+///
+/// ```text
+/// Login failure for user "<username>" from <ip>
+/// Login failure for token from <ip>
+/// Login lockout for user "<username>" from <ip> for <seconds> seconds
+/// Login rejected for user "<username>" from <ip>: locked out
+/// ```
+pub async fn get_auth_info(
+    req: &HttpRequest,
+    auth_data: &AuthData,
+    auth: Option<BasicAuth>,
+) -> Result<AuthInfo, Error> {
+    let client_ip = client_ip(req);
+    let anonymous = || AuthInfo {
+        ip: client_ip,
+        ..Default::default()
+    };
+
+    let bearer = match auth {
+        Some(_) => None,
+        None => Authorization::<Bearer>::parse(req).ok(),
+    };
+
+    let (user_id, password) = match (&auth, &bearer) {
+        (Some(auth), _) => (Some(auth.user_id()), auth.password()),
+        (None, Some(bearer)) => (None, Some(bearer.as_ref().token())),
+        (None, None) => return Ok(anonymous()),
+    };
+
+    let who = match user_id {
+        Some(user_id) => format!("user {:?}", user_id),
+        None => "token".to_owned(),
+    };
+    let ip = client_ip.map(|ip| ip.to_string()).unwrap_or_default();
+
+    let mut keys = vec![(
+        format!("ip:{ip}"),
+        login_backoff(
+            SETTINGS.login_max_failures_per_ip,
+            SETTINGS.login_lockout_max,
+        ),
+    )];
+
+    // Usernames are throttled regardless of the client, so that guesses spread
+    // across many addresses are slowed down as well. Their lockout is shorter,
+    // so that failed logins of others only briefly lock out the user.
+    if let Some(user_id) = user_id {
+        keys.push((
+            format!("user:{user_id}"),
+            login_backoff(
+                SETTINGS.login_max_failures_per_user,
+                SETTINGS.login_lockout_max_per_user,
+            ),
+        ));
+    }
+
+    let now = Instant::now();
+
+    let locked_out = keys
+        .iter()
+        .filter_map(|(key, backoff)| LOGIN_THROTTLE.locked_out(key, backoff, now))
+        .max();
+
+    if let Some(remaining) = locked_out {
+        log::warn!("Login rejected for {} from {}: locked out", who, ip);
+
+        audit::record(Record {
+            user: user_id,
+            ip: client_ip,
+            ..Record::new(Outcome::LoginLockedOut)
+        });
+
+        return Err(Error::TooManyLoginFailures(
+            req.clone(),
+            remaining.as_secs_f64().ceil() as u64,
+        ));
+    }
+
+    let credentials = match (user_id, password) {
+        (Some(username), Some(password)) => Some(Credentials::Password { username, password }),
+        (None, Some(token)) => Some(Credentials::Token(token)),
+        (_, None) => None,
+    };
+
+    let auth_info = match credentials {
+        Some(credentials) => match AUTHENTICATOR.authenticate(auth_data, credentials).await {
+            Ok(auth_info) => auth_info,
+            Err(err) => {
+                log::error!("Failed to authenticate {} from {}: {}", who, ip, err);
+
+                return Ok(anonymous());
+            }
+        },
+        None => None,
+    };
+
+    if let Some(auth_info) = auth_info {
+        // Only the failures of this user are forgiven, a successful login must not
+        // allow a client to try further passwords for other users
+        if let Some((key, _)) = keys.get(1) {
+            LOGIN_THROTTLE.succeed(key);
+        }
+
+        return Ok(AuthInfo {
+            ip: client_ip,
+            ..auth_info
+        });
+    }
+
+    log::warn!("Login failure for {} from {}", who, ip);
+
+    audit::record(Record {
+        user: user_id,
+        ip: client_ip,
+        ..Record::new(Outcome::LoginFailed)
     });
 
-    AuthInfo { username }
+    let lockout = keys
+        .iter()
+        .filter_map(|(key, backoff)| LOGIN_THROTTLE.fail(key, backoff, now))
+        .max();
+
+    if let Some(lockout) = lockout {
+        log::warn!(
+            "Login lockout for {} from {} for {} seconds",
+            who,
+            ip,
+            lockout.as_secs()
+        );
+    }
+
+    Ok(anonymous())
 }
 
 #[allow(unused)]
@@ -129,3 +808,664 @@ pub fn redirect(ty: RedirectType) -> HttpResponse {
         .insert_header((LOCATION, ty.to()))
         .finish()
 }
+
+#[test]
+fn test_is_accessible() {
+    let auth_data = AuthData::try_from(
+        r#"{
+        "users": {
+            "Customer": {
+                "passwd": "-",
+                "groups": [ "Premium" ],
+                "packages": {
+                    "be.bastelstu.user": "*",
+                    "!be.bastelstu.user": "$v ~ beta"
+                }
+            },
+            "Chargeback": {
+                "passwd": "-",
+                "groups": [ "Premium" ],
+                "packages": {
+                    "!be.bastelstu.premium": "$v >= 2.0.0"
+                }
+            },
+            "Insider": {
+                "passwd": "-",
+                "groups": [ "Premium" ],
+                "packages": {
+                    "be.bastelstu.free": "*"
+                }
+            }
+        },
+        "groups": {
+            "Premium": {
+                "be.bastelstu.premium": "*",
+                "!be.bastelstu.free": "$v ~ dev"
+            }
+        },
+        "packages": {
+            "be.bastelstu.free": "*",
+            "!*": "$v ~ alpha"
+        }
+    }"#,
+    )
+    .unwrap();
+
+    let accessible = |package_id: &str, version: &str, username: Option<&str>| {
+        let version = Version::try_from(version).unwrap();
+        let auth_info = AuthInfo {
+            username: username.map(ToOwned::to_owned),
+            token: None,
+            licence: None,
+            user: None,
+            ip: None,
+        };
+
+        is_accessible(package_id, &version, None, &auth_info, &auth_data)
+    };
+
+    // Global deny beats global allow
+    assert!(accessible("be.bastelstu.free", "1.0.0", None));
+    assert!(!accessible("be.bastelstu.free", "1.0.0 Alpha 1", None));
+
+    // Group deny beats global allow
+    assert!(!accessible(
+        "be.bastelstu.free",
+        "1.0.0 dev 1",
+        Some("Customer")
+    ));
+    assert!(accessible("be.bastelstu.free", "1.0.0 dev 1", None));
+
+    // User allow beats group deny
+    assert!(accessible(
+        "be.bastelstu.free",
+        "1.0.0 dev 1",
+        Some("Insider")
+    ));
+
+    // Group allow beats global deny
+    assert!(accessible(
+        "be.bastelstu.premium",
+        "1.0.0 Alpha 1",
+        Some("Customer")
+    ));
+    assert!(!accessible("be.bastelstu.premium", "1.0.0", None));
+
+    // User deny beats group allow
+    assert!(accessible(
+        "be.bastelstu.premium",
+        "1.5.0",
+        Some("Chargeback")
+    ));
+    assert!(!accessible(
+        "be.bastelstu.premium",
+        "2.0.0",
+        Some("Chargeback")
+    ));
+
+    // User deny beats user allow
+    assert!(accessible("be.bastelstu.user", "1.0.0", Some("Customer")));
+    assert!(!accessible(
+        "be.bastelstu.user",
+        "1.0.0 Beta 1",
+        Some("Customer")
+    ));
+}
+
+#[test]
+fn test_is_accessible_valid_until() {
+    let auth_data = AuthData::try_from(
+        r#"{
+        "users": {
+            "Customer": {
+                "passwd": "-",
+                "valid_until": "2025-06-01",
+                "groups": [ "Basic", { "name": "Premium", "valid_until": "2025-03-01" } ],
+                "packages": {
+                    "be.bastelstu.user": "*"
+                }
+            }
+        },
+        "groups": {
+            "Basic": {
+                "be.bastelstu.basic": "*"
+            },
+            "Premium": {
+                "be.bastelstu.premium": "*"
+            }
+        },
+        "packages": {
+            "be.bastelstu.free": "$released < 2025-01-01"
+        }
+    }"#,
+    )
+    .unwrap();
+
+    let version = Version::try_from("1.0.0").unwrap();
+    let customer = AuthInfo {
+        username: Some("Customer".to_owned()),
+        token: None,
+        licence: None,
+        user: None,
+        ip: None,
+    };
+    let accessible = |package_id: &str, released: Option<Date>| {
+        is_accessible(package_id, &version, released, &customer, &auth_data)
+    };
+
+    assert!(accessible("be.bastelstu.user", Some(Date::new(2025, 6, 1))));
+    assert!(!accessible(
+        "be.bastelstu.user",
+        Some(Date::new(2025, 6, 2))
+    ));
+    assert!(!accessible("be.bastelstu.user", None));
+
+    assert!(accessible(
+        "be.bastelstu.basic",
+        Some(Date::new(2025, 6, 1))
+    ));
+    assert!(!accessible(
+        "be.bastelstu.basic",
+        Some(Date::new(2025, 6, 2))
+    ));
+
+    assert!(accessible(
+        "be.bastelstu.premium",
+        Some(Date::new(2025, 3, 1))
+    ));
+    assert!(!accessible(
+        "be.bastelstu.premium",
+        Some(Date::new(2025, 3, 2))
+    ));
+
+    assert!(accessible(
+        "be.bastelstu.free",
+        Some(Date::new(2024, 12, 31))
+    ));
+    assert!(!accessible(
+        "be.bastelstu.free",
+        Some(Date::new(2025, 1, 1))
+    ));
+    assert!(!accessible("be.bastelstu.free", None));
+}
+
+#[test]
+fn test_is_accessible_nested_groups() {
+    let auth_data = AuthData::try_from(
+        r#"{
+        "users": {
+            "Customer": {
+                "passwd": "-",
+                "groups": [ "Premium" ]
+            }
+        },
+        "groups": {
+            "Basic": {
+                "be.bastelstu.basic": "*",
+                "!be.bastelstu.premium": "$v ~ beta"
+            },
+            "Premium": {
+                "groups": [ "Basic" ],
+                "packages": {
+                    "be.bastelstu.premium": "*"
+                }
+            }
+        }
+    }"#,
+    )
+    .unwrap();
+
+    let customer = AuthInfo {
+        username: Some("Customer".to_owned()),
+        token: None,
+        licence: None,
+        user: None,
+        ip: None,
+    };
+    let accessible = |package_id: &str, version: &str| {
+        let version = Version::try_from(version).unwrap();
+
+        is_accessible(package_id, &version, None, &customer, &auth_data)
+    };
+
+    assert!(accessible("be.bastelstu.basic", "1.0.0"));
+    assert!(accessible("be.bastelstu.premium", "1.0.0"));
+    assert!(!accessible("be.bastelstu.premium", "1.0.0 Beta 1"));
+    assert!(!accessible("be.bastelstu.other", "1.0.0"));
+}
+
+#[test]
+fn test_is_accessible_api_token() {
+    use crate::auth::TokenDigest;
+
+    let auth_data = AuthData::try_from(
+        r#"{
+        "users": {
+            "Customer": {
+                "passwd": "-",
+                "packages": {
+                    "be.bastelstu.a": "*",
+                    "be.bastelstu.b": "*"
+                },
+                "tokens": {
+                    "ci": {
+                        "sha256": "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08",
+                        "packages": [ "be.bastelstu.a" ]
+                    },
+                    "deploy": {
+                        "sha256": "b7bd55c11b781b0ccc43aa6e57f9dadf0660e9d1d4e27e0979ee43a407d454ae"
+                    },
+                    "expired": {
+                        "sha256": "fa64ea1e82e1206f828ab2a02917c7e92accb98e3b95881a1b4ad52b914b66e3",
+                        "expires": "2025-06-01"
+                    },
+                    "revoked": {
+                        "sha256": "4bb47f186df233e48b09d241ee4defb821add0c35ac8311469fe1522c6813dd5",
+                        "revoked": true
+                    }
+                }
+            }
+        },
+        "packages": {
+            "be.bastelstu.free": "*"
+        }
+    }"#,
+    )
+    .unwrap();
+
+    let (username, name, token) = auth_data.find_token(&TokenDigest::of("test")).unwrap();
+    assert_eq!((username, name), ("Customer", "ci"));
+    assert!(token.is_valid(Date::new(2100, 1, 1)));
+    assert!(auth_data.find_token(&TokenDigest::of("root")).is_none());
+
+    let tokens = &auth_data.users["Customer"].tokens;
+    assert!(tokens["deploy"].is_valid(Date::new(2100, 1, 1)));
+
+    // Tokens expire after their expiry date
+    assert!(tokens["expired"].is_valid(Date::new(2025, 6, 1)));
+    assert!(!tokens["expired"].is_valid(Date::new(2025, 6, 2)));
+
+    // Revoked tokens are rejected regardless of the date
+    assert!(!tokens["revoked"].is_valid(Date::new(2025, 1, 1)));
+
+    let accessible = |package_id: &str, token: Option<&str>| {
+        let version = Version::try_from("1.0.0").unwrap();
+        let auth_info = AuthInfo {
+            username: Some("Customer".to_owned()),
+            token: token.map(ToOwned::to_owned),
+            licence: None,
+            user: None,
+            ip: None,
+        };
+
+        is_accessible(package_id, &version, None, &auth_info, &auth_data)
+    };
+
+    assert!(accessible("be.bastelstu.a", None));
+    assert!(accessible("be.bastelstu.b", None));
+
+    // Scoped tokens only grant the user’s permissions for matching packages
+    assert!(accessible("be.bastelstu.a", Some("ci")));
+    assert!(!accessible("be.bastelstu.b", Some("ci")));
+    assert!(accessible("be.bastelstu.free", Some("ci")));
+
+    // Unscoped tokens grant all of the user’s permissions
+    assert!(accessible("be.bastelstu.a", Some("deploy")));
+    assert!(accessible("be.bastelstu.b", Some("deploy")));
+}
+
+#[test]
+fn test_is_accessible_licence() {
+    let auth_data = AuthData::try_from(
+        r#"{
+        "users": {
+            "Customer": {
+                "passwd": "-",
+                "packages": {
+                    "be.bastelstu.a": "*",
+                    "!be.bastelstu.c": "*"
+                },
+                "licences": {
+                    "123456": {
+                        "serial_key": "-",
+                        "packages": {
+                            "be.bastelstu.b": "*",
+                            "be.bastelstu.c": "*"
+                        }
+                    },
+                    "654321": {
+                        "serial_key": "-",
+                        "valid_until": "2025-06-01",
+                        "packages": {
+                            "be.bastelstu.d": "*"
+                        }
+                    }
+                }
+            }
+        }
+    }"#,
+    )
+    .unwrap();
+
+    let (username, _) = auth_data.find_licence("123456").unwrap();
+    assert_eq!(username, "Customer");
+    assert!(auth_data.find_licence("Customer").is_none());
+
+    let accessible = |package_id: &str, licence: Option<&str>, released: Option<Date>| {
+        let version = Version::try_from("1.0.0").unwrap();
+        let auth_info = AuthInfo {
+            username: Some("Customer".to_owned()),
+            token: None,
+            licence: licence.map(ToOwned::to_owned),
+            user: None,
+            ip: None,
+        };
+
+        is_accessible(package_id, &version, released, &auth_info, &auth_data)
+    };
+
+    // Licences grant their packages in addition to the user’s permissions
+    assert!(!accessible("be.bastelstu.b", None, None));
+    assert!(accessible("be.bastelstu.b", Some("123456"), None));
+    assert!(accessible("be.bastelstu.a", Some("123456"), None));
+    assert!(!accessible("be.bastelstu.b", Some("654321"), None));
+
+    // The licence is more specific than the user
+    assert!(!accessible("be.bastelstu.c", None, None));
+    assert!(accessible("be.bastelstu.c", Some("123456"), None));
+
+    // The licence’s grants are limited by its `valid_until` date
+    assert!(accessible(
+        "be.bastelstu.d",
+        Some("654321"),
+        Some(Date::new(2025, 6, 1))
+    ));
+    assert!(!accessible(
+        "be.bastelstu.d",
+        Some("654321"),
+        Some(Date::new(2025, 6, 2))
+    ));
+}
+
+#[test]
+fn test_is_listed() {
+    let auth_data = AuthData::try_from(
+        r#"{
+        "users": {
+            "Customer": {
+                "passwd": "-",
+                "packages": {
+                    "be.bastelstu.customer.a": "*"
+                }
+            }
+        },
+        "packages": {
+            "be.bastelstu.free": "$v < 2.0.0"
+        },
+        "visibility": {
+            "be.bastelstu.*": "accessible",
+            "be.bastelstu.free": "all",
+            "a.b.c.d.*": "accessible",
+            "*d.package": "all"
+        }
+    }"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        auth_data.visibility("be.bastelstu.customer.a"),
+        Visibility::Accessible
+    );
+    assert_eq!(auth_data.visibility("be.bastelstu.free"), Visibility::All);
+    assert_eq!(auth_data.visibility("com.example.foo"), Visibility::All);
+    // The escaped `.` must not make a pattern more specific
+    assert_eq!(auth_data.visibility("a.b.c.d.package"), Visibility::All);
+
+    let listed = |package_id: &str, version: &str, username: Option<&str>| {
+        let version = Version::try_from(version).unwrap();
+        let auth_info = AuthInfo {
+            username: username.map(ToOwned::to_owned),
+            ..Default::default()
+        };
+
+        is_listed(package_id, &version, None, &auth_info, &auth_data)
+    };
+
+    // Hidden unless accessible
+    assert!(!listed("be.bastelstu.customer.a", "1.0.0", None));
+    assert!(listed("be.bastelstu.customer.a", "1.0.0", Some("Customer")));
+    assert!(!listed(
+        "be.bastelstu.customer.b",
+        "1.0.0",
+        Some("Customer")
+    ));
+
+    // The longest pattern wins
+    assert!(listed("be.bastelstu.free", "1.0.0", None));
+    assert!(listed("be.bastelstu.free", "2.0.0", None));
+
+    // Listed by default
+    assert!(listed("com.example.foo", "1.0.0", None));
+}
+
+#[test]
+fn test_explain_access() {
+    let auth_data = AuthData::try_from(
+        r#"{
+        "users": {
+            "Customer": {
+                "passwd": "-",
+                "groups": [ { "name": "Premium", "valid_until": "2025-01-01" } ],
+                "packages": {
+                    "be.bastelstu.user": "*"
+                }
+            }
+        },
+        "groups": {
+            "Premium": {
+                "be.bastelstu.*": "*",
+                "!be.bastelstu.free": "$v ~ dev"
+            }
+        },
+        "packages": {
+            "be.bastelstu.free": "*"
+        }
+    }"#,
+    )
+    .unwrap();
+
+    let customer = AuthInfo {
+        username: Some("Customer".to_owned()),
+        token: None,
+        licence: None,
+        user: None,
+        ip: None,
+    };
+    let version = Version::try_from("1.0.0").unwrap();
+    let released = Some(Date::new(2025, 6, 1));
+
+    let explanation = explain_access(
+        "be.bastelstu.premium",
+        &version,
+        released,
+        &customer,
+        &auth_data,
+    );
+    assert!(!explanation.accessible);
+
+    let rules = explanation
+        .rules
+        .iter()
+        .map(|rule| {
+            (
+                rule.source,
+                rule.deny,
+                rule.pattern.to_string(),
+                rule.ruleset_matches,
+                rule.covered,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        rules,
+        vec![
+            (
+                Source::User("Customer"),
+                false,
+                "be.bastelstu.user".to_owned(),
+                None,
+                true
+            ),
+            (
+                Source::Group("Premium"),
+                true,
+                "be.bastelstu.free".to_owned(),
+                None,
+                true
+            ),
+            (
+                Source::Group("Premium"),
+                false,
+                "be.bastelstu.*".to_owned(),
+                Some(true),
+                false
+            ),
+            (
+                Source::Everyone,
+                false,
+                "be.bastelstu.free".to_owned(),
+                None,
+                true
+            ),
+        ]
+    );
+
+    // The explanation stops at the deciding rule
+    let explanation = explain_access("be.bastelstu.user", &version, None, &customer, &auth_data);
+    assert!(explanation.accessible);
+    assert_eq!(explanation.rules.len(), 1);
+    assert!(explanation.to_string().ends_with(
+        "user “Customer”: be.bastelstu.user: pattern matches, ruleset matches\nAccess granted.\n"
+    ));
+}
+
+#[test]
+fn test_forwarded_client_ip() {
+    let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+    let trusted = ["10.0.0.0/8".parse().unwrap(), "::1".parse().unwrap()];
+
+    // Untrusted peers cannot claim another address
+    assert_eq!(
+        forwarded_client_ip(ip("192.0.2.1"), &["203.0.113.1"], &trusted),
+        ip("192.0.2.1")
+    );
+
+    // Trusted proxies are skipped from the right
+    assert_eq!(
+        forwarded_client_ip(
+            ip("::1"),
+            &["198.51.100.1", "203.0.113.1", " 10.0.0.2"],
+            &trusted
+        ),
+        ip("203.0.113.1")
+    );
+    assert_eq!(
+        forwarded_client_ip(ip("::ffff:10.0.0.1"), &[], &trusted),
+        ip("10.0.0.1")
+    );
+    assert_eq!(
+        forwarded_client_ip(ip("10.0.0.1"), &["10.0.0.2"], &trusted),
+        ip("10.0.0.2")
+    );
+    assert_eq!(
+        forwarded_client_ip(ip("10.0.0.1"), &["192.0.2.1", "unknown"], &trusted),
+        ip("10.0.0.1")
+    );
+}
+
+#[test]
+fn test_is_accessible_network() {
+    let auth_data = AuthData::try_from(
+        r#"{
+        "users": {
+            "Customer": {
+                "passwd": "-",
+                "packages": {
+                    "!be.bastelstu.beta": "$v ~ rc"
+                }
+            }
+        },
+        "networks": {
+            "Office": {
+                "addresses": [ "192.0.2.0/24", "2001:db8::/32" ],
+                "packages": {
+                    "be.bastelstu.beta": "*",
+                    "!be.bastelstu.free": "$v ~ dev"
+                }
+            }
+        },
+        "packages": {
+            "be.bastelstu.free": "*",
+            "!be.bastelstu.beta": "*"
+        }
+    }"#,
+    )
+    .unwrap();
+
+    let accessible = |package_id: &str, version: &str, username: Option<&str>, ip: &str| {
+        let version = Version::try_from(version).unwrap();
+        let auth_info = AuthInfo {
+            username: username.map(ToOwned::to_owned),
+            token: None,
+            licence: None,
+            user: None,
+            ip: Some(ip.parse().unwrap()),
+        };
+
+        is_accessible(package_id, &version, None, &auth_info, &auth_data)
+    };
+
+    // Network allow beats global deny
+    assert!(accessible("be.bastelstu.beta", "1.0.0", None, "192.0.2.1"));
+    assert!(accessible(
+        "be.bastelstu.beta",
+        "1.0.0",
+        None,
+        "2001:db8::1"
+    ));
+    assert!(!accessible(
+        "be.bastelstu.beta",
+        "1.0.0",
+        None,
+        "198.51.100.1"
+    ));
+
+    // Network deny beats global allow
+    assert!(!accessible(
+        "be.bastelstu.free",
+        "1.0.0 dev 1",
+        None,
+        "192.0.2.1"
+    ));
+    assert!(accessible(
+        "be.bastelstu.free",
+        "1.0.0 dev 1",
+        None,
+        "198.51.100.1"
+    ));
+
+    // Users get the network’s grants on top of their own, their own deny still wins
+    assert!(accessible(
+        "be.bastelstu.beta",
+        "1.0.0",
+        Some("Customer"),
+        "192.0.2.1"
+    ));
+    assert!(!accessible(
+        "be.bastelstu.beta",
+        "1.0.0 RC 1",
+        Some("Customer"),
+        "192.0.2.1"
+    ));
+}
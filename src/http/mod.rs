@@ -25,7 +25,8 @@ use crate::SETTINGS;
 use actix_web::{middleware, App, HttpServer};
 use helpers::{get_auth_info, is_accessible, redirect, RedirectType};
 use routes::{
-    about, assets, download, favicon, health, login, main_xslt, package_update_xml, source_code,
+    about, api, assets, download, download_link, explain, favicon, feed, health, login, main_xslt,
+    package_update_xml, source_code,
 };
 
 pub async fn run() -> crate::Result<()> {
@@ -44,7 +45,11 @@ pub async fn run() -> crate::Result<()> {
                     )),
             )
             .service(health)
+            .service(api())
+            .service(feed())
             .service(download())
+            .service(explain)
+            .service(download_link)
             .service(main_xslt)
             .service(assets)
             .service(source_code())
@@ -58,3 +63,102 @@ pub async fn run() -> crate::Result<()> {
     .await
     .map_err(Into::into)
 }
+
+/// Stores the users and packages the tests of the routes run against. They
+/// are the same for every test, as the tests share the global state.
+///
+/// - `be.bastelstu.free` 1.0.0 and 1.1.0 are public.
+/// - `be.bastelstu.hidden` 1.0.0 and 2.0.0 are only listed to users that may
+///   access them, the user `customer` (password `test`) may access 1.0.0.
+/// - `be.bastelstu.shop` 1.0.0 and 2.0.0 are listed to everyone, `customer`
+///   may access 1.0.0.
+///
+/// The versions were modified in the order 1.0.0 of `free`, `hidden` and
+/// `shop`, 1.1.0 of `free`, and then 2.0.0 of `hidden` and `shop`.
+#[cfg(test)]
+pub fn init_test_data() {
+    use crate::{
+        package::{
+            list_reader::{PackageInfo, PackageList},
+            xml::{PackageInformation, PackageName, PackageXML},
+        },
+        version::Version,
+        AUTH_DATA, PACKAGE_LIST, UPTIME,
+    };
+    use std::{
+        sync::{Arc, Once},
+        time::{Duration, SystemTime},
+    };
+
+    static INIT: Once = Once::new();
+
+    let package = |name: &str, version: &str, modified: u64| PackageInfo {
+        data: PackageXML {
+            name: name.to_owned(),
+            package_information: PackageInformation {
+                name: vec![PackageName {
+                    name: name.to_owned(),
+                    language: None,
+                }],
+                version: Version::try_from(version).unwrap(),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        hash: String::new(),
+        mtime: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(modified)),
+        released: None,
+    };
+
+    INIT.call_once(|| {
+        let dir = std::env::temp_dir().join(format!("tps-routes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("auth.json"),
+            r#"{
+                "users": {
+                    "customer": {
+                        "passwd": "Bcrypt:$2y$08$n1CcYF1ABM8gNMjJqi8Z4uHEL1OYQLPUM05sXaEuoOiRrJHxq6yt2",
+                        "packages": {
+                            "be.bastelstu.hidden": "$v < 2.0.0",
+                            "be.bastelstu.shop": "$v < 2.0.0"
+                        }
+                    }
+                },
+                "packages": {
+                    "be.bastelstu.free": "*"
+                },
+                "visibility": {
+                    "be.bastelstu.hidden": "accessible"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let auth_data = crate::auth::read_auth_data(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        AUTH_DATA.store(Arc::new(auth_data));
+        PACKAGE_LIST.store(Some(Arc::new(PackageList {
+            packages: vec![
+                vec![
+                    package("be.bastelstu.free", "1.0.0", 1_000),
+                    package("be.bastelstu.free", "1.1.0", 4_000),
+                ],
+                vec![
+                    package("be.bastelstu.hidden", "1.0.0", 2_000),
+                    package("be.bastelstu.hidden", "2.0.0", 5_000),
+                ],
+                vec![
+                    package("be.bastelstu.shop", "1.0.0", 3_000),
+                    package("be.bastelstu.shop", "2.0.0", 6_000),
+                ],
+            ],
+            purchase_info: Default::default(),
+            updated_at: SystemTime::UNIX_EPOCH + Duration::from_secs(10_000),
+            updated_in: Duration::default(),
+            scanned_version_count: 6,
+        })));
+        let _ = UPTIME.set(std::time::Instant::now());
+    });
+}
@@ -17,10 +17,12 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use crate::{
+    audit::{self, Outcome, Record},
+    auth::{DownloadLink, DownloadQuota, Visibility},
     http::{
         error::Error::{
-            AccessDenied, PackageListUnavailable, PackageReadFailed, PaymentRequired,
-            UnknownPackage, UnknownPackageVersion,
+            AccessDenied, DownloadQuotaExceeded, PackageListUnavailable, PackageReadFailed,
+            PaymentRequired, UnknownPackage, UnknownPackageVersion,
         },
         get_auth_info, is_accessible, redirect, RedirectType,
     },
@@ -37,11 +39,13 @@ use actix_web_httpauth::extractors::basic::BasicAuth;
 use once_cell::sync::Lazy;
 use serde::Deserialize;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     fs::OpenOptions,
     io::Write,
+    net::IpAddr,
     path::PathBuf,
     sync::{Mutex, RwLock},
+    time::{Duration, Instant, SystemTime},
 };
 
 static DOWNLOAD_COUNTERS: Lazy<RwLock<HashMap<String, Mutex<usize>>>> =
@@ -123,12 +127,92 @@ fn log_download(package_id: &str, version_str: &str) {
     write_counter_file(package_id, version_str, *counter);
 }
 
+/// The recent downloads of a user with a download quota.
+#[derive(Debug, Default)]
+struct Downloads {
+    /// The window of the user’s quota when they last downloaded.
+    window: Duration,
+    times: VecDeque<Instant>,
+}
+
+/// The recent downloads of every user with a download quota.
+///
+/// Users whose downloads have all left their window are forgotten whenever
+/// the number of users doubled, so that the history stays bounded without
+/// scanning all users on every download.
+#[derive(Debug, Default)]
+struct DownloadHistory {
+    users: HashMap<String, Downloads>,
+    prune_at: usize,
+}
+
+/// The number of users tracked before the first users are forgotten.
+const DOWNLOAD_HISTORY_PRUNE_MIN: usize = 1024;
+
+static DOWNLOAD_HISTORY: Lazy<Mutex<DownloadHistory>> =
+    Lazy::new(|| Mutex::new(DownloadHistory::default()));
+
+/// Counts a download towards the user’s quota, if the quota permits another
+/// download within the window. Otherwise returns the number of seconds
+/// until the oldest download of the window expires.
+fn count_download(
+    history: &mut DownloadHistory,
+    username: &str,
+    quota: DownloadQuota,
+    now: Instant,
+) -> Result<(), u64> {
+    let window = Duration::from_secs(quota.window);
+
+    if !history.users.contains_key(username) && history.users.len() >= history.prune_at {
+        history.users.retain(|_, downloads| {
+            downloads
+                .times
+                .back()
+                .is_some_and(|newest| now.duration_since(*newest) < downloads.window)
+        });
+        history.prune_at = (2 * history.users.len()).max(DOWNLOAD_HISTORY_PRUNE_MIN);
+    }
+
+    let downloads = history.users.entry(username.to_owned()).or_default();
+    downloads.window = window;
+
+    while downloads
+        .times
+        .front()
+        .is_some_and(|download| now.duration_since(*download) >= window)
+    {
+        downloads.times.pop_front();
+    }
+
+    if downloads.times.len() >= quota.downloads as usize {
+        let retry_after = downloads
+            .times
+            .front()
+            .map_or(window, |oldest| window - now.duration_since(*oldest));
+
+        if downloads.times.is_empty() {
+            history.users.remove(username);
+        }
+
+        return Err(retry_after.as_secs_f64().ceil() as u64);
+    }
+
+    downloads.times.push_back(now);
+
+    Ok(())
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DownloadRequest {
     api_version: Option<String>,
     package_name: Option<String>,
     package_version: Option<String>,
+
+    /// The parameters of a signed download link, see [`DownloadLink`].
+    expires: Option<u64>,
+    ip: Option<IpAddr>,
+    signature: Option<String>,
 }
 
 pub fn download() -> impl HttpServiceFactory {
@@ -160,7 +244,7 @@ async fn download_latest(
     package_id: web::Path<String>,
 ) -> impl Responder {
     let auth_data = AUTH_DATA.load_full();
-    let auth_info = get_auth_info(&auth_data, auth);
+    let auth_info = get_auth_info(&req, &auth_data, auth).await?;
 
     if let Some(package_list) = PACKAGE_LIST.load_full() {
         'outer: for package in &package_list.packages {
@@ -169,9 +253,10 @@ async fn download_latest(
                     continue 'outer;
                 }
 
+                let released = version.released;
                 let version = &version.data.package_information.version;
 
-                if is_accessible(&package_id, version, &auth_info, &auth_data) {
+                if is_accessible(&package_id, version, released, &auth_info, &auth_data) {
                     return Ok(redirect(RedirectType::Other(format!(
                         "{}/{}/{}/",
                         *host,
@@ -182,6 +267,20 @@ async fn download_latest(
             }
 
             // The package exists but the user is not authorized to download it
+            audit::record(Record {
+                user: auth_info.username.as_deref(),
+                token: auth_info.token.as_deref(),
+                licence: auth_info.licence.as_deref(),
+                ip: auth_info.ip,
+                package: Some(&package_id),
+                ..Record::new(Outcome::Unauthorized)
+            });
+
+            // Hidden packages are treated as if they did not exist
+            if auth_data.visibility(&package_id) == Visibility::Accessible {
+                return Err(UnknownPackage(req, package_id.to_string()));
+            }
+
             return Err(AccessDenied(req));
         }
 
@@ -198,7 +297,7 @@ async fn get_download_package(
     path: web::Path<(String, String)>,
     web::Query(query): web::Query<DownloadRequest>,
 ) -> impl Responder {
-    download_package(req, auth, path, query)
+    download_package(req, auth, path, query).await
 }
 
 async fn post_download_package(
@@ -212,12 +311,15 @@ async fn post_download_package(
         api_version: params.api_version.or(query.api_version),
         package_name: params.package_name.or(query.package_name),
         package_version: params.package_version.or(query.package_version),
+        expires: query.expires,
+        ip: query.ip,
+        signature: query.signature,
     };
 
-    download_package(req, auth, path, params)
+    download_package(req, auth, path, params).await
 }
 
-fn download_package(
+async fn download_package(
     req: HttpRequest,
     auth: Option<BasicAuth>,
     path: web::Path<(String, String)>,
@@ -225,7 +327,7 @@ fn download_package(
 ) -> impl Responder {
     let (package_id, version_str) = path.into_inner();
     let auth_data = AUTH_DATA.load_full();
-    let auth_info = get_auth_info(&auth_data, auth);
+    let auth_info = get_auth_info(&req, &auth_data, auth).await?;
     // The path makes sure that the version is valid
     let version = Version::try_from(version_str.replace('_', " ").as_str()).unwrap();
 
@@ -234,7 +336,43 @@ fn download_package(
 
     let file_path = SETTINGS.package_dir.join(&package_id).join(filename);
 
-    if is_accessible(&package_id, &version, &auth_info, &auth_data) {
+    let released = PACKAGE_LIST.load().as_ref().and_then(|package_list| {
+        package_list
+            .find(&package_id, &version)
+            .and_then(|package| package.released)
+    });
+
+    // Signed download links grant access without credentials
+    let is_signed = match (
+        &SETTINGS.download_link_secret,
+        params.expires,
+        &params.signature,
+    ) {
+        (Some(secret), Some(expires), Some(signature)) => {
+            let link = DownloadLink {
+                package_id: &package_id,
+                version: &version,
+                expires,
+                ip: params.ip,
+            };
+
+            link.is_valid(secret, signature, auth_info.ip, SystemTime::now())
+        }
+        _ => false,
+    };
+
+    let record = Record {
+        user: auth_info.username.as_deref(),
+        token: auth_info.token.as_deref(),
+        licence: auth_info.licence.as_deref(),
+        ip: auth_info.ip,
+        package: Some(&package_id),
+        version: Some(version.to_string()),
+        api_version: params.api_version.as_deref(),
+        ..Record::new(Outcome::Downloaded)
+    };
+
+    if is_signed || is_accessible(&package_id, &version, released, &auth_info, &auth_data) {
         let file = match actix_files::NamedFile::open(file_path) {
             Ok(file) => file,
             Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
@@ -257,10 +395,39 @@ fn download_package(
             parameters: vec![DispositionParam::Filename(download_name)],
         };
 
+        let quota = auth_info
+            .user(&auth_data)
+            .and_then(|user| auth_data.download_quota(user));
+
+        if let (Some(username), Some(quota)) = (&auth_info.username, quota) {
+            let mut history = DOWNLOAD_HISTORY.lock().unwrap();
+
+            if let Err(retry_after) = count_download(&mut history, username, quota, Instant::now())
+            {
+                log::warn!("{} exceeded the download quota", username);
+
+                audit::record(Record {
+                    outcome: Outcome::QuotaExceeded,
+                    ..record
+                });
+
+                return Err(DownloadQuotaExceeded(req, retry_after));
+            }
+        }
+
         if SETTINGS.enable_statistics {
             log_download(&package_id, &version_str);
         }
 
+        audit::record(Record {
+            outcome: if is_signed {
+                Outcome::DownloadedSigned
+            } else {
+                Outcome::Downloaded
+            },
+            ..record
+        });
+
         return Ok(file
             .use_etag(true)
             .use_last_modified(true)
@@ -269,18 +436,97 @@ fn download_package(
     } else if file_path.is_file() {
         let who = auth_info
             .username
-            .or_else(|| req.peer_addr().map(|addr| addr.to_string()))
+            .clone()
+            .or_else(|| auth_info.ip.map(|ip| ip.to_string()))
             .unwrap_or_else(|| "An anonymous user".to_owned());
 
         log::debug!("{} tried to download {}/{}", who, package_id, version_str);
 
-        match params.api_version {
-            Some(api_version) if api_version == "2.1" => {
-                return Err(PaymentRequired(req, package_id, version));
+        // Packages that are only visible to users with access must not be
+        // distinguishable from packages that do not exist
+        if auth_data.visibility(&package_id) == Visibility::Accessible {
+            audit::record(Record {
+                outcome: Outcome::Unauthorized,
+                ..record
+            });
+
+            return Err(UnknownPackage(req, package_id));
+        }
+
+        match params.api_version.as_deref() {
+            Some("2.1") => {
+                audit::record(Record {
+                    outcome: Outcome::PaymentRequired,
+                    ..record
+                });
+
+                let purchase_info = PACKAGE_LIST.load().as_ref().and_then(|package_list| {
+                    package_list
+                        .purchase_info
+                        .get(&package_id)
+                        .cloned()
+                        .map(Box::new)
+                });
+
+                return Err(PaymentRequired(req, package_id, version, purchase_info));
+            }
+            _ => {
+                audit::record(Record {
+                    outcome: Outcome::Unauthorized,
+                    ..record
+                });
+
+                return Err(AccessDenied(req));
             }
-            _ => return Err(AccessDenied(req)),
         }
     }
 
     Err(UnknownPackage(req, package_id))
 }
+
+#[test]
+fn test_count_download() {
+    let quota = DownloadQuota {
+        downloads: 2,
+        window: 60,
+    };
+    let mut history = DownloadHistory::default();
+    let start = Instant::now();
+    let at = |secs| start + Duration::from_secs(secs);
+
+    assert_eq!(count_download(&mut history, "user", quota, at(0)), Ok(()));
+    assert_eq!(count_download(&mut history, "user", quota, at(10)), Ok(()));
+    assert_eq!(count_download(&mut history, "user", quota, at(20)), Err(40));
+    assert_eq!(count_download(&mut history, "other", quota, at(20)), Ok(()));
+
+    // Denied downloads do not count towards the quota
+    assert_eq!(count_download(&mut history, "user", quota, at(59)), Err(1));
+
+    // The window is rolling
+    assert_eq!(count_download(&mut history, "user", quota, at(60)), Ok(()));
+    assert_eq!(count_download(&mut history, "user", quota, at(61)), Err(9));
+    assert_eq!(count_download(&mut history, "user", quota, at(70)), Ok(()));
+
+    // A quota of zero downloads denies every download
+    let quota = DownloadQuota {
+        downloads: 0,
+        window: 60,
+    };
+    assert_eq!(count_download(&mut history, "third", quota, at(0)), Err(60));
+    assert!(!history.users.contains_key("third"));
+
+    // Users without downloads within their window are forgotten
+    let quota = DownloadQuota {
+        downloads: 1,
+        window: 60,
+    };
+    for n in 0..DOWNLOAD_HISTORY_PRUNE_MIN {
+        assert_eq!(
+            count_download(&mut history, &format!("user{n}"), quota, at(200)),
+            Ok(())
+        );
+    }
+    assert_eq!(history.users.len(), DOWNLOAD_HISTORY_PRUNE_MIN);
+    assert!(!history.users.contains_key("user"));
+    assert!(!history.users.contains_key("other"));
+}
@@ -0,0 +1,87 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::{
+    http::{
+        error::Error::{self, AccessDenied, FileNotFound, PackageListUnavailable},
+        get_auth_info,
+        header::Host,
+        helpers::{is_admin, sign_download_link},
+    },
+    version::Version,
+    AUTH_DATA, PACKAGE_LIST,
+};
+use actix_web::{
+    get,
+    http::header::{CacheControl, CacheDirective, ContentType},
+    web, HttpRequest, HttpResponse, Responder,
+};
+use actix_web_httpauth::extractors::basic::BasicAuth;
+use serde::Deserialize;
+use std::net::IpAddr;
+
+#[derive(Debug, Deserialize)]
+pub struct DownloadLinkRequest {
+    package: String,
+    version: String,
+
+    /// How long (in seconds) the link is valid, `download_link_ttl` by default.
+    valid_for: Option<u64>,
+    ip: Option<IpAddr>,
+}
+
+/// Creates a signed link to download a version of a package without
+/// credentials, only available to members of the `admin_group`.
+#[get("/admin/download-link")]
+pub async fn download_link(
+    req: HttpRequest,
+    auth: Option<BasicAuth>,
+    host: Host,
+    web::Query(query): web::Query<DownloadLinkRequest>,
+) -> Result<impl Responder, Error> {
+    let auth_data = AUTH_DATA.load_full();
+    let auth_info = get_auth_info(&req, &auth_data, auth).await?;
+
+    if !is_admin(&auth_info, &auth_data) {
+        return Err(AccessDenied(req));
+    }
+
+    let package_list = PACKAGE_LIST
+        .load_full()
+        .ok_or_else(|| PackageListUnavailable(req.clone()))?;
+
+    let version = Version::try_from(query.version.as_str())
+        .ok()
+        .filter(|version| package_list.find(&query.package, version).is_some())
+        .ok_or_else(|| FileNotFound(req, format!("{} {}", query.package, query.version)))?;
+
+    let response = match sign_download_link(&query.package, &version, query.valid_for, query.ip) {
+        Some(path) => HttpResponse::Ok().body(format!("{}{}", *host, path)),
+        None => HttpResponse::NotImplemented()
+            .body("Signed download links are disabled, no download_link_secret is configured."),
+    };
+
+    Ok(response
+        .customize()
+        .insert_header(CacheControl(vec![
+            CacheDirective::NoCache,
+            CacheDirective::NoStore,
+            CacheDirective::Private,
+        ]))
+        .insert_header(ContentType::plaintext()))
+}
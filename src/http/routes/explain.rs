@@ -0,0 +1,83 @@
+// Copyright (C) 2013 - 2021 Tim Düsterhus
+// Copyright (C) 2021 Maximilian Mader
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::{
+    http::{
+        error::Error::{self, AccessDenied, FileNotFound, PackageListUnavailable},
+        get_auth_info,
+        helpers::{explain_hosted_access, is_admin},
+    },
+    version::Version,
+    AUTH_DATA, PACKAGE_LIST,
+};
+use actix_web::{
+    get,
+    http::header::{CacheControl, CacheDirective, ContentType},
+    web, HttpRequest, HttpResponse, Responder,
+};
+use actix_web_httpauth::extractors::basic::BasicAuth;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct ExplainRequest {
+    package: String,
+    version: String,
+    user: Option<String>,
+}
+
+/// Explains why a user may or may not access a version of a package,
+/// only available to members of the `admin_group`.
+#[get("/admin/explain")]
+pub async fn explain(
+    req: HttpRequest,
+    auth: Option<BasicAuth>,
+    web::Query(query): web::Query<ExplainRequest>,
+) -> Result<impl Responder, Error> {
+    let auth_data = AUTH_DATA.load_full();
+    let auth_info = get_auth_info(&req, &auth_data, auth).await?;
+
+    if !is_admin(&auth_info, &auth_data) {
+        return Err(AccessDenied(req));
+    }
+
+    let package_list = PACKAGE_LIST
+        .load_full()
+        .ok_or_else(|| PackageListUnavailable(req.clone()))?;
+
+    let explanation = Version::try_from(query.version.as_str())
+        .ok()
+        .and_then(|version| {
+            explain_hosted_access(
+                &package_list,
+                &auth_data,
+                query.user.as_deref().filter(|user| !user.is_empty()),
+                &query.package,
+                &version,
+            )
+        })
+        .ok_or_else(|| FileNotFound(req, format!("{} {}", query.package, query.version)))?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(CacheControl(vec![
+            CacheDirective::NoCache,
+            CacheDirective::NoStore,
+            CacheDirective::Private,
+        ]))
+        .content_type(ContentType::plaintext())
+        .body(explanation))
+}
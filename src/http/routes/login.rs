@@ -17,6 +17,7 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use crate::{
+    audit::{self, Outcome, Record},
     http::{error::Error::AccessDenied, get_auth_info, redirect, RedirectType},
     AUTH_DATA,
 };
@@ -39,7 +40,19 @@ pub fn login() -> impl HttpServiceFactory {
 
 async fn perform_login(req: HttpRequest, auth: Option<BasicAuth>) -> impl Responder {
     let auth_data = AUTH_DATA.load_full();
-    let auth_info = get_auth_info(&auth_data, auth);
+    let auth_info = get_auth_info(&req, &auth_data, auth).await?;
+
+    // Credentials are sent with every request, thus only the explicit
+    // logins are recorded rather than every authenticated request
+    if auth_info.username.is_some() {
+        audit::record(Record {
+            user: auth_info.username.as_deref(),
+            token: auth_info.token.as_deref(),
+            licence: auth_info.licence.as_deref(),
+            ip: auth_info.ip,
+            ..Record::new(Outcome::LoginSucceeded)
+        });
+    }
 
     auth_info
         .username
@@ -18,6 +18,7 @@
 
 use crate::{
     http::{
+        error::Error::{self, IoError},
         get_auth_info,
         header::{Host, Language},
         SETTINGS,
@@ -25,17 +26,18 @@ use crate::{
     templates::{MainTemplate, Template},
     AUTH_DATA, LICENSE_INFO,
 };
-use actix_web::{get, http::header::VARY, HttpResponse, Responder};
+use actix_web::{get, http::header::VARY, HttpRequest, HttpResponse, Responder};
 use actix_web_httpauth::extractors::basic::BasicAuth;
 
 #[get("/style/main.xslt")]
 pub async fn main_xslt(
+    req: HttpRequest,
     auth: Option<BasicAuth>,
     language: Language,
     host: Host,
-) -> std::io::Result<impl Responder> {
+) -> Result<impl Responder, Error> {
     let auth_data = AUTH_DATA.load_full();
-    let auth_info = get_auth_info(&auth_data, auth);
+    let auth_info = get_auth_info(&req, &auth_data, auth).await?;
 
     Ok(HttpResponse::Ok()
         .insert_header((VARY, "accept-language"))
@@ -50,6 +52,6 @@ pub async fn main_xslt(
                 auth_info,
             }
             .render()
-            .map_err(|err| err.into_io_error())?,
+            .map_err(|err| IoError(req.clone(), err.into_io_error()))?,
         ))
 }
@@ -17,8 +17,12 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 mod about;
+mod api;
 mod assets;
 mod download;
+mod download_link;
+mod explain;
+mod feed;
 mod health;
 mod login;
 mod main_xslt;
@@ -26,8 +30,12 @@ mod package_update_xml;
 mod source_code;
 
 pub use about::*;
+pub use api::*;
 pub use assets::*;
 pub use download::*;
+pub use download_link::*;
+pub use explain::*;
+pub use feed::*;
 pub use health::*;
 pub use login::*;
 pub use main_xslt::*;
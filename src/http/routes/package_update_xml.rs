@@ -21,9 +21,10 @@ use crate::{
         error::Error::{IoError, NotAcceptable, PackageListUnavailable},
         get_auth_info,
         header::{negotiate_language, not_modified, Host, Language},
+        helpers::access_key,
         redirect, RedirectType, SETTINGS,
     },
-    templates::{PackageUpdateXmlTemplate, Template},
+    templates::{PackageListHtmlTemplate, PackageUpdateXmlTemplate, Template},
     AUTH_DATA, PACKAGE_LIST, UPTIME,
 };
 use actix_web::{
@@ -65,7 +66,7 @@ async fn get_xml(
     host: Host,
     web::Query(query): web::Query<PackageUpdateXmlRequest>,
 ) -> impl Responder {
-    response(req, auth, user_lang, host, query)
+    response(req, auth, user_lang, host, query).await
 }
 
 async fn post_xml(
@@ -81,10 +82,10 @@ async fn post_xml(
         package_version: params.package_version.or(query.package_version),
     };
 
-    response(req, auth, user_lang, host, params)
+    response(req, auth, user_lang, host, params).await
 }
 
-fn response(
+async fn response(
     req: HttpRequest,
     auth: Option<BasicAuth>,
     mut user_lang: Language,
@@ -92,19 +93,22 @@ fn response(
     params: PackageUpdateXmlRequest,
 ) -> impl Responder {
     let accept = Accept::parse(&req);
-    let is_acceptable = match accept.as_ref().ok() {
-        Some(accept) if !accept.ranked().is_empty() => accept.ranked().iter().any(|mime| {
+    let preferred = match accept.as_ref().ok() {
+        Some(accept) if !accept.ranked().is_empty() => accept.ranked().into_iter().find(|mime| {
             matches!(
                 (mime.type_(), mime.subtype()),
-                (mime::TEXT, mime::XML) | (mime::STAR, _)
+                (mime::TEXT, mime::XML) | (mime::TEXT, mime::HTML) | (mime::STAR, _)
             )
         }),
-        Some(_) | None => true,
+        Some(_) | None => Some(mime::TEXT_XML),
     };
 
-    if !is_acceptable {
-        return Err(NotAcceptable(req, accept.unwrap()));
-    }
+    // Browsers get the HTML rendering, everything else, WoltLab clients in
+    // particular, gets the XML
+    let is_html = match preferred {
+        Some(mime) => (mime.type_(), mime.subtype()) == (mime::TEXT, mime::HTML),
+        None => return Err(NotAcceptable(req, accept.unwrap())),
+    };
 
     let xml_lang = req
         .match_info()
@@ -118,7 +122,7 @@ fn response(
 
     let user_lang_string = user_lang.to_string();
     let auth_data = AUTH_DATA.load_full();
-    let auth_info = get_auth_info(&auth_data, auth);
+    let auth_info = get_auth_info(&req, &auth_data, auth).await?;
     let package_list = PACKAGE_LIST.load_full();
 
     match package_list {
@@ -140,7 +144,9 @@ fn response(
                 .unwrap_or_default()
                 .as_secs();
 
-            let etag_content = match (&auth_info.username, &xml_lang) {
+            let username = access_key(&auth_info, &auth_data);
+
+            let mut etag_content = match (&username, &xml_lang) {
                 (Some(username), Some(xml_lang)) => {
                     format!(
                         "{},{},{},{}",
@@ -158,6 +164,10 @@ fn response(
                 }
             };
 
+            if is_html {
+                etag_content.insert_str(0, "html,");
+            }
+
             let etag = ETag(EntityTag::new(
                 !SETTINGS.deterministic,
                 BASE64.encode(etag_content),
@@ -172,29 +182,99 @@ fn response(
                     .finish());
             }
 
+            // Unlike WoltLab clients, browsers do not request a language specific list
+            let xml_lang = match xml_lang {
+                None if is_html => Some(user_lang.package_language()),
+                xml_lang => xml_lang,
+            };
+
+            let list = PackageUpdateXmlTemplate {
+                host: host.clone(),
+                server_version: crate::built_info::version(),
+                package_list,
+                user_lang: user_lang_string,
+                xml_lang,
+                auth_data,
+                auth_info,
+
+                uptime: UPTIME.get().unwrap().elapsed(),
+                deterministic: SETTINGS.deterministic,
+                start_time: std::time::Instant::now(),
+            };
+
+            let (content_type, body) = if is_html {
+                let body = PackageListHtmlTemplate {
+                    title: SETTINGS.page_title.as_ref(),
+                    list,
+                }
+                .render();
+
+                ("text/html; charset=utf-8", body)
+            } else {
+                ("text/xml; charset=utf-8", list.render())
+            };
+
             Ok(HttpResponse::Ok()
                 .insert_header((ETAG, etag))
                 .insert_header((LAST_MODIFIED, last_modified))
-                .insert_header((CONTENT_TYPE, "text/xml; charset=utf-8"))
+                .insert_header((CONTENT_TYPE, content_type))
                 .insert_header((VARY, "accept, accept-language"))
-                .body(
-                    PackageUpdateXmlTemplate {
-                        host: host.clone(),
-                        server_version: crate::built_info::version(),
-                        package_list,
-                        user_lang: user_lang_string,
-                        xml_lang,
-                        auth_data,
-                        auth_info,
-
-                        uptime: UPTIME.get().unwrap().elapsed(),
-                        deterministic: SETTINGS.deterministic,
-                        start_time: std::time::Instant::now(),
-                    }
-                    .render()
-                    .map_err(|err| IoError(req, err.into_io_error()))?,
-                ))
+                .body(body.map_err(|err| IoError(req, err.into_io_error()))?))
         }
         None => Err(PackageListUnavailable(req)),
     }
 }
+
+#[actix_web::test]
+async fn test_negotiation() {
+    use actix_web::{
+        http::{header::ACCEPT, StatusCode},
+        test, App,
+    };
+
+    crate::http::init_test_data();
+
+    let app = test::init_service(App::new().service(package_update_xml())).await;
+
+    let mut etags = Vec::new();
+    for (accept, content_type) in [
+        (Some("text/html"), "text/html; charset=utf-8"),
+        (
+            Some("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"),
+            "text/html; charset=utf-8",
+        ),
+        (Some("*/*"), "text/xml; charset=utf-8"),
+        (Some("text/xml"), "text/xml; charset=utf-8"),
+        (None, "text/xml; charset=utf-8"),
+    ] {
+        let mut req = test::TestRequest::get().uri("/");
+        if let Some(accept) = accept {
+            req = req.insert_header((ACCEPT, accept));
+        }
+
+        let res = test::call_service(&app, req.to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK, "{accept:?}");
+        assert_eq!(
+            res.headers().get(CONTENT_TYPE).unwrap(),
+            content_type,
+            "{accept:?}"
+        );
+        etags.push(res.headers().get(ETAG).unwrap().clone());
+    }
+
+    // Caches must not serve the HTML to clients asking for the XML
+    assert_eq!(etags[0], etags[1]);
+    assert_ne!(etags[0], etags[2]);
+    assert_eq!(etags[2], etags[3]);
+    assert_eq!(etags[2], etags[4]);
+
+    for accept in ["application/json", "image/*"] {
+        let req = test::TestRequest::get()
+            .uri("/")
+            .insert_header((ACCEPT, accept))
+            .to_request();
+
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), StatusCode::NOT_ACCEPTABLE, "{accept}");
+    }
+}
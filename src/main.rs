@@ -32,9 +32,13 @@ use std::{
     path::PathBuf,
     sync::{mpsc, Arc},
 };
+use url::Url;
 
+mod audit;
 mod auth;
 mod built_info;
+mod cli;
+mod date;
 mod fluent;
 mod http;
 mod nom;
@@ -56,6 +60,51 @@ pub struct Settings {
     pub deterministic: bool,
     pub ssl: bool,
 
+    /// How long (in seconds) successfully verified credentials are remembered.
+    pub credential_cache_ttl: u64,
+    /// How many verified credentials are remembered at most.
+    pub credential_cache_size: usize,
+
+    /// The number of failed logins from a single IP address that trigger a lockout.
+    pub login_max_failures_per_ip: u32,
+    /// The number of failed logins for a single username, from any IP address, that trigger a lockout.
+    pub login_max_failures_per_user: u32,
+    /// The duration (in seconds) of the first lockout, doubled with every further failure.
+    pub login_lockout_base: u64,
+    /// The maximum duration (in seconds) of a lockout.
+    pub login_lockout_max: u64,
+    /// The maximum duration (in seconds) of the lockout of a username, shorter than
+    /// `login_lockout_max` so that failed logins of others only briefly lock out the user.
+    pub login_lockout_max_per_user: u64,
+
+    /// Authenticate users unknown to the auth data by POSTing their credentials to this URL.
+    pub auth_callback_url: Option<Url>,
+    /// The timeout (in seconds) of requests to the authentication callback.
+    pub auth_callback_timeout: u64,
+    /// How long (in seconds) the responses of the authentication callback are remembered.
+    pub auth_callback_cache_ttl: u64,
+    /// How many responses of the authentication callback are remembered at most.
+    pub auth_callback_cache_size: usize,
+
+    /// Members of this group may use the administrative endpoints.
+    pub admin_group: Option<String>,
+
+    /// Proxies whose `X-Forwarded-For` header is trusted to contain the client’s address.
+    #[serde(default, deserialize_with = "auth::deserialize_network_list")]
+    pub trusted_proxies: Vec<auth::Network>,
+
+    /// The key of signed download links, links are disabled if not set.
+    pub download_link_secret: Option<String>,
+    /// How long (in seconds) signed download links are valid by default.
+    pub download_link_ttl: u64,
+
+    /// Append a record of every download, denied download and login to this file.
+    pub audit_log: Option<PathBuf>,
+    /// The size (in bytes) at which the audit log is rotated, zero disables the rotation by size.
+    pub audit_log_max_size: u64,
+    /// Rotate the audit log whenever the date changes.
+    pub audit_log_daily: bool,
+
     pub page_title: Option<String>,
     pub host: Option<String>,
 }
@@ -72,6 +121,30 @@ impl Default for Settings {
             deterministic: true,
             ssl: false,
 
+            credential_cache_ttl: 300,
+            credential_cache_size: 1024,
+
+            login_max_failures_per_ip: 20,
+            login_max_failures_per_user: 5,
+            login_lockout_base: 1,
+            login_lockout_max: 900,
+            login_lockout_max_per_user: 60,
+
+            auth_callback_url: None,
+            auth_callback_timeout: 5,
+            auth_callback_cache_ttl: 60,
+            auth_callback_cache_size: 1024,
+
+            admin_group: None,
+            trusted_proxies: Vec::new(),
+
+            download_link_secret: None,
+            download_link_ttl: 7 * 24 * 60 * 60,
+
+            audit_log: None,
+            audit_log_max_size: 10 * 1024 * 1024,
+            audit_log_daily: false,
+
             page_title: None,
             host: None,
         }
@@ -101,17 +174,12 @@ pub static AUTH_DATA: Lazy<ArcSwap<AuthData>> =
 pub static UPTIME: OnceCell<std::time::Instant> = OnceCell::new();
 
 async fn init_auth_data() -> crate::Result<()> {
-    let path = SETTINGS.package_dir.join("auth.json");
-    let auth_data = if path.exists() {
-        match auth::read_auth_json(path).map_err(Into::into) {
-            Ok(auth_data) => auth_data,
-            Err(err) => {
-                log::error!("Failed to read `auth.json`: {}", err);
-                return Err(err);
-            }
+    let auth_data = match auth::read_auth_data(&SETTINGS.package_dir).map_err(Into::into) {
+        Ok(auth_data) => auth_data,
+        Err(err) => {
+            log::error!("Failed to read auth data: {}", err);
+            return Err(err);
         }
-    } else {
-        AuthData::default()
     };
 
     AUTH_DATA.store(Arc::new(auth_data));
@@ -135,6 +203,23 @@ async fn init_package_list() -> crate::Result<()> {
 
 #[actix_web::main]
 async fn main() -> crate::Result<()> {
+    let mut args = std::env::args().skip(1);
+
+    if let Some(command) = args.next() {
+        let env = env_logger::Env::default()
+            .default_filter_or("tims_package_server=warn")
+            .default_write_style_or("auto");
+
+        env_logger::init_from_env(env);
+
+        if let Err(err) = cli::run(&command, args) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+
+        return Ok(());
+    }
+
     if unsafe { ::libc::getuid() } == 0 {
         panic!("Cowardly refusing to keep the process alive as root.");
     }
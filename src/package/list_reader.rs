@@ -16,11 +16,13 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use crate::{package::xml::PackageXML, version::Version};
+use crate::{date::Date, package::xml::PackageXML, version::Version};
 use once_cell::sync::Lazy;
 use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::{ffi::OsStr, path::Path};
+use std::{collections::HashMap, ffi::OsStr, path::Path};
+use url::Url;
 
 static PACKAGE_ID_REGEX: Lazy<Regex> = Lazy::new(|| {
     RegexBuilder::new(r#"^([a-z0-9_-]+\.[a-z0-9_-]+(?:\.[a-z0-9_-]+)+)$"#)
@@ -31,19 +33,52 @@ static PACKAGE_ID_REGEX: Lazy<Regex> = Lazy::new(|| {
 
 pub type PackageVersions = Vec<PackageInfo>;
 
+/// The name of the file within a package’s directory that holds its [`PurchaseInfo`].
+pub const PURCHASE_INFO_FILE: &str = "purchase.json";
+
+/// Where and how to buy a package, shown to users who
+/// may not access all of its versions.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PurchaseInfo {
+    /// The package’s page in a store.
+    pub store_url: Option<Url>,
+
+    /// A free-form note on the price, e.g. “€ 15 including one year of updates”.
+    pub price: Option<String>,
+
+    /// How to contact the vendor, e.g. an email address.
+    pub contact: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct PackageList {
     pub packages: Vec<PackageVersions>,
+
+    /// The purchase information of the packages by their identifier.
+    pub purchase_info: HashMap<String, PurchaseInfo>,
     pub updated_at: std::time::SystemTime,
     pub updated_in: std::time::Duration,
     pub scanned_version_count: u32,
 }
 
+impl PackageList {
+    pub fn find(&self, package_id: &str, version: &Version) -> Option<&PackageInfo> {
+        self.packages
+            .iter()
+            .find(|versions| versions[0].data.name == package_id)?
+            .iter()
+            .find(|package| package.data.package_information.version == *version)
+    }
+}
+
 #[derive(Debug)]
 pub struct PackageInfo {
     pub data: PackageXML,
     pub hash: String,
     pub mtime: Option<std::time::SystemTime>,
+
+    /// The declared release date, falling back to the modification time of the archive.
+    pub released: Option<Date>,
 }
 
 fn get_package_xml_from_tar<T: std::io::Read>(
@@ -129,13 +164,28 @@ fn read_package_archive(
         format!("{:x}", hasher.finalize())
     };
 
+    let released = Date::try_from(package_xml.package_information.date.trim())
+        .ok()
+        .or_else(|| mtime.map(Date::from));
+
     Ok(PackageInfo {
         data: package_xml,
         hash,
         mtime,
+        released,
     })
 }
 
+fn read_purchase_info(path: &Path) -> crate::Result<Option<PurchaseInfo>> {
+    let contents = match std::fs::read(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    Ok(Some(serde_json::from_slice(&contents)?))
+}
+
 fn scan_package_dir(
     path: &Path,
     package_name: &str,
@@ -162,6 +212,10 @@ fn scan_package_dir(
             continue;
         }
 
+        if name == PURCHASE_INFO_FILE {
+            continue;
+        }
+
         let version_str = path
             .file_stem()
             .ok_or("Path has no file stem")?
@@ -214,6 +268,7 @@ fn scan_package_dir(
 pub fn scan_packages() -> crate::Result<PackageList> {
     let updated_in = std::time::Instant::now();
     let mut packages = Vec::new();
+    let mut purchase_info = HashMap::new();
     let mut scanned_version_count: u32 = 0;
 
     for entry in crate::SETTINGS.package_dir.read_dir()? {
@@ -247,6 +302,16 @@ pub fn scan_packages() -> crate::Result<PackageList> {
                     log::warn!("No versions for package {} found", name);
                 } else {
                     packages.push(versions);
+
+                    match read_purchase_info(&path.join(PURCHASE_INFO_FILE)) {
+                        Ok(Some(info)) => {
+                            purchase_info.insert(name.to_owned(), info);
+                        }
+                        Ok(None) => (),
+                        Err(err) => {
+                            log::error!("Failed to read purchase information of {}: {}", name, err);
+                        }
+                    }
                 }
             }
             Err(err) => {
@@ -259,6 +324,7 @@ pub fn scan_packages() -> crate::Result<PackageList> {
 
     let list = PackageList {
         packages,
+        purchase_info,
         updated_at: std::time::SystemTime::now(),
         updated_in: updated_in.elapsed(),
         scanned_version_count,
@@ -272,3 +338,27 @@ pub fn scan_packages() -> crate::Result<PackageList> {
 
     Ok(list)
 }
+
+#[test]
+fn test_purchase_info() {
+    let info: PurchaseInfo = serde_json::from_str(
+        r#"{
+        "store_url": "https://store.example.com/be.bastelstu.example",
+        "price": "€ 15",
+        "contact": "sales@example.com"
+    }"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        info.store_url.unwrap().as_str(),
+        "https://store.example.com/be.bastelstu.example"
+    );
+    assert_eq!(info.price.as_deref(), Some("€ 15"));
+    assert_eq!(info.contact.as_deref(), Some("sales@example.com"));
+
+    let info: PurchaseInfo = serde_json::from_str(r#"{ "price": "€ 15" }"#).unwrap();
+    assert!(info.store_url.is_none());
+
+    assert!(serde_json::from_str::<PurchaseInfo>(r#"{ "store_url": "store" }"#).is_err());
+}
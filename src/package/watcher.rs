@@ -16,21 +16,30 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use super::list_reader::PURCHASE_INFO_FILE;
 use crate::{AUTH_DATA, PACKAGE_LIST, SETTINGS};
 use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
 use std::{
     ffi::OsStr,
-    path::Path,
+    path::{Path, PathBuf},
     sync::{
-        mpsc::{Receiver, Sender},
+        mpsc::{Receiver, RecvTimeoutError, Sender},
         Arc, Mutex,
     },
+    time::Duration,
 };
 
+/// How often the watched directories of htpasswd files outside of the
+/// package directory are updated to match the auth data.
+const HTPASSWD_WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
 pub struct PackageWatcher<'a> {
     inner: RecommendedWatcher,
     path: &'a Path,
     scanning: Arc<Mutex<()>>,
+
+    /// The directories of htpasswd files outside of `path` that are watched.
+    htpasswd_dirs: Vec<PathBuf>,
 }
 
 impl<'a> PackageWatcher<'a> {
@@ -43,6 +52,7 @@ impl<'a> PackageWatcher<'a> {
             inner,
             path,
             scanning: Arc::new(Mutex::new(())),
+            htpasswd_dirs: Vec::new(),
         };
 
         Ok(watcher)
@@ -61,13 +71,13 @@ impl<'a> PackageWatcher<'a> {
         std::thread::spawn(move || {
             let scanning = scanning.lock().unwrap();
 
-            log::info!("Reading auth.json");
-            match crate::auth::read_auth_json(SETTINGS.package_dir.join("auth.json")) {
+            log::info!("Reading auth data");
+            match crate::auth::read_auth_data(&SETTINGS.package_dir) {
                 Ok(auth_data) => {
                     AUTH_DATA.store(Arc::new(auth_data));
                 }
                 Err(err) => {
-                    log::error!("Failed to read `auth.json`: {}", err);
+                    log::error!("Failed to read auth data: {}", err);
                 }
             }
 
@@ -96,8 +106,21 @@ impl<'a> PackageWatcher<'a> {
             | DebouncedEvent::Chmod(ref path)
             | DebouncedEvent::Remove(ref path)
             | DebouncedEvent::Rename(ref path, _) => {
+                let is_htpasswd_file = || {
+                    AUTH_DATA
+                        .load()
+                        .htpasswd
+                        .iter()
+                        .any(|file| file.path == *path)
+                };
+
                 if path.extension() == Some(OsStr::new("tar"))
+                    || path.file_name() == Some(OsStr::new(PURCHASE_INFO_FILE))
                     || path == &self.path.join("auth.json")
+                    || path.starts_with(self.path.join("auth.d"))
+                    || path == &self.path.join("auth.sqlite")
+                    || path == &self.path.join("auth.sqlite-wal")
+                    || is_htpasswd_file()
                 {
                     log::trace!("Re-scan triggered by event: {:#?}", event);
                     self.start_scan();
@@ -113,15 +136,52 @@ impl<'a> PackageWatcher<'a> {
         }
     }
 
+    /// Watches the directories of the htpasswd files outside of the package
+    /// directory. The directories are watched instead of the files themselves,
+    /// as the files are usually replaced rather than modified.
+    fn update_htpasswd_watches(&mut self) {
+        let mut dirs = AUTH_DATA
+            .load()
+            .htpasswd
+            .iter()
+            .filter(|file| !file.path.starts_with(self.path))
+            .filter_map(|file| file.path.parent().map(Path::to_path_buf))
+            .collect::<Vec<_>>();
+
+        dirs.sort();
+        dirs.dedup();
+
+        for dir in &self.htpasswd_dirs {
+            if !dirs.contains(dir) {
+                if let Err(err) = self.inner.unwatch(dir) {
+                    log::error!("Failed to stop watching {:?}: {}", dir, err);
+                }
+            }
+        }
+
+        for dir in &dirs {
+            if !self.htpasswd_dirs.contains(dir) {
+                if let Err(err) = self.inner.watch(dir, RecursiveMode::NonRecursive) {
+                    log::error!("Failed to watch htpasswd directory {:?}: {}", dir, err);
+                }
+            }
+        }
+
+        self.htpasswd_dirs = dirs;
+    }
+
     pub fn start_watcher(&mut self, rx: Receiver<DebouncedEvent>) {
         loop {
-            match rx.recv() {
+            self.update_htpasswd_watches();
+
+            match rx.recv_timeout(HTPASSWD_WATCH_INTERVAL) {
                 Ok(DebouncedEvent::Error(err, Some(path))) => {
                     log::error!("Watch error in path {:?}: {:?}", path, err);
                 }
                 Ok(DebouncedEvent::Error(err, None)) => {
                     log::error!("Watch error: {:?}", err);
                 }
+                Err(RecvTimeoutError::Timeout) => (),
                 Err(err) => {
                     log::error!("Generic watch error, stopping loop: {:?}", err);
                     break;
@@ -142,6 +202,10 @@ impl std::fmt::Debug for PackageWatcher<'_> {
 
 impl Drop for PackageWatcher<'_> {
     fn drop(&mut self) {
+        for dir in &self.htpasswd_dirs {
+            let _ = self.inner.unwatch(dir);
+        }
+
         self.inner.unwatch(self.path).unwrap();
     }
 }
@@ -37,7 +37,13 @@ pub fn is_accessible(
     let version = &package.data.package_information.version;
     let package_id = &package.data.name;
 
-    if crate::http::helpers::is_accessible(package_id, version, auth_info, auth_data) {
+    if crate::http::helpers::is_accessible(
+        package_id,
+        version,
+        package.released,
+        auth_info,
+        auth_data,
+    ) {
         Ok("true")
     } else {
         Ok("false")
@@ -56,6 +62,15 @@ pub fn rfc3339(t: &SystemTime, _: &dyn askama::Values) -> askama::Result<String>
     Ok(humantime::format_rfc3339(*t).to_string())
 }
 
+pub fn rfc3339_seconds(t: &SystemTime, _: &dyn askama::Values) -> askama::Result<String> {
+    Ok(humantime::format_rfc3339_seconds(*t).to_string())
+}
+
+/// Turns a package identifier or version into an HTML `id`.
+pub fn anchor(s: &str, _: &dyn askama::Values) -> askama::Result<String> {
+    Ok(s.replace([' ', '.'], "-"))
+}
+
 fn get_for_language<'a, T: xml::HasLanguage>(
     items: &'a [T],
     lang: &Option<LanguageIdentifier>,
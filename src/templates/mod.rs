@@ -19,10 +19,14 @@
 mod filters;
 
 use crate::{
-    auth::AuthData, fluent, http::helpers::AuthInfo, package::list_reader::PackageList, LicenseInfo,
+    auth::AuthData,
+    fluent,
+    http::helpers::AuthInfo,
+    package::list_reader::{PackageInfo, PackageList},
+    LicenseInfo,
 };
 pub use askama::Template;
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc, time::SystemTime};
 use unic_langid::LanguageIdentifier;
 
 trait Assets {
@@ -78,6 +82,196 @@ pub struct PackageUpdateXmlTemplate {
     pub start_time: std::time::Instant,
 }
 
+impl PackageUpdateXmlTemplate {
+    /// The packages and versions listed to the user.
+    fn listed_packages(&self) -> Vec<Vec<&PackageInfo>> {
+        crate::http::helpers::listed_packages(&self.package_list, &self.auth_info, &self.auth_data)
+    }
+}
+
+/// The human-facing rendering of the package list, rendered from the same
+/// data as the XML list.
+#[derive(Template)]
+#[template(path = "packageList.html")]
+pub struct PackageListHtmlTemplate {
+    pub title: Option<&'static String>,
+    pub list: PackageUpdateXmlTemplate,
+}
+
+impl Assets for PackageListHtmlTemplate {
+    fn get_host(&self) -> &str {
+        &self.list.host
+    }
+}
+
+impl PackageListHtmlTemplate {
+    /// The packages listed to the user, sorted by their name.
+    fn packages(&self) -> Vec<Vec<&PackageInfo>> {
+        let mut packages = self.list.listed_packages();
+
+        packages.sort_by_cached_key(|package| self.name(newest(package)));
+        packages
+    }
+
+    /// The identifiers of the packages that have their own entry on the page to link to.
+    fn listed<'a>(&self, packages: &[Vec<&'a PackageInfo>]) -> HashSet<&'a str> {
+        packages
+            .iter()
+            .map(|versions| newest(versions).data.name.as_str())
+            .collect()
+    }
+
+    fn name(&self, package: &PackageInfo) -> String {
+        filters::package_name(
+            &package.data.package_information.name,
+            &(),
+            &self.list.xml_lang,
+        )
+        .unwrap_or_default()
+    }
+
+    fn description(&self, package: &PackageInfo) -> String {
+        filters::package_description(
+            &package.data.package_information.description,
+            &(),
+            &self.list.xml_lang,
+        )
+        .unwrap_or_default()
+    }
+
+    fn is_accessible(&self, package: &PackageInfo) -> bool {
+        crate::http::helpers::is_accessible(
+            &package.data.name,
+            &package.data.package_information.version,
+            package.released,
+            &self.list.auth_info,
+            &self.list.auth_data,
+        )
+    }
+
+    fn is_fully_accessible(&self, versions: &[&PackageInfo]) -> bool {
+        versions.iter().all(|version| self.is_accessible(version))
+    }
+
+    /// Whether the optional packages column is shown.
+    fn has_optionals(&self, versions: &[&PackageInfo]) -> bool {
+        versions
+            .iter()
+            .any(|version| !version.data.optional_packages.is_empty())
+    }
+
+    /// The data used by the search of `main.js`, as a JavaScript expression.
+    fn search_index(&self, packages: &[Vec<&PackageInfo>]) -> String {
+        let packages = packages
+            .iter()
+            .map(|versions| {
+                let newest = newest(versions);
+
+                serde_json::json!({
+                    "id": newest.data.name,
+                    "name": self.name(newest),
+                    "description": self.description(newest),
+                    "author": newest.data.author_information.author,
+                    "authorURL": newest.data.author_information.author_url,
+                    "versions": versions
+                        .iter()
+                        .rev()
+                        .map(|version| version.data.package_information.version.to_string())
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Prevent a `</script>` in the data from closing the script element
+        serde_json::Value::from(packages)
+            .to_string()
+            .replace('<', "\\u003c")
+    }
+}
+
+/// The newest version of a package, the versions are sorted from the oldest to the newest.
+fn newest<'a>(versions: &[&'a PackageInfo]) -> &'a PackageInfo {
+    versions.last().expect("packages have at least one version")
+}
+
+/// The number of versions listed in a feed, feed readers only need the recent releases.
+const FEED_ENTRIES: usize = 50;
+
+#[derive(Template)]
+#[template(path = "feed.atom", escape = "xml")]
+pub struct FeedTemplate {
+    pub host: String,
+    pub feed_url: String,
+    pub user_lang: String,
+    pub lang: Option<LanguageIdentifier>,
+    /// The package of a per-package feed, `None` for the global feed.
+    pub package_id: Option<String>,
+    pub package_list: Arc<PackageList>,
+    pub auth_data: Arc<AuthData>,
+    pub auth_info: AuthInfo,
+}
+
+impl FeedTemplate {
+    /// The newest [`FEED_ENTRIES`] versions the user may download, from the
+    /// newest to the oldest release.
+    fn entries(&self) -> Vec<&PackageInfo> {
+        let mut entries = crate::http::helpers::listed_packages(
+            &self.package_list,
+            &self.auth_info,
+            &self.auth_data,
+        )
+        .into_iter()
+        .filter(|versions| {
+            self.package_id
+                .as_ref()
+                .is_none_or(|package_id| versions[0].data.name == *package_id)
+        })
+        .flatten()
+        .filter(|package| {
+            crate::http::helpers::is_accessible(
+                &package.data.name,
+                &package.data.package_information.version,
+                package.released,
+                &self.auth_info,
+                &self.auth_data,
+            )
+        })
+        .collect::<Vec<_>>();
+
+        entries.sort_by(|a, b| self.updated(b).cmp(self.updated(a)));
+        entries.truncate(FEED_ENTRIES);
+        entries
+    }
+
+    fn updated<'a>(&'a self, package: &'a PackageInfo) -> &'a SystemTime {
+        package
+            .mtime
+            .as_ref()
+            .unwrap_or(&self.package_list.updated_at)
+    }
+
+    fn title(&self) -> String {
+        let package = self.package_id.as_ref().and_then(|package_id| {
+            self.package_list
+                .packages
+                .iter()
+                .find(|versions| versions[0].data.name == *package_id)
+                .and_then(|versions| versions.last())
+        });
+
+        match package {
+            Some(package) => {
+                let package =
+                    filters::package_name(&package.data.package_information.name, &(), &self.lang)
+                        .unwrap_or_default();
+
+                fluent!(self.user_lang, "feed-title-package", { package })
+            }
+            None => fluent!(self.user_lang, "feed-title"),
+        }
+    }
+}
+
 #[derive(Template)]
 #[template(path = "about.html")]
 pub struct AboutTemplate {